@@ -0,0 +1,870 @@
+use crate::gitignore::IgnoreFilter;
+use crate::pattern::Patterns;
+use crate::sort::{change_time, Sort, SortBy};
+use crate::tree::{Metadata, Node, NodeKind, Omitted, Report, Tree, WalkError};
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
+
+type Filter = Box<dyn Fn(&Path) -> bool>;
+
+/// What [`Node::name`] holds.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Naming {
+    /// The file name, or the root path as given
+    #[default]
+    FileName,
+    /// The path starting with the root as given
+    Full,
+    /// The canonical absolute path
+    Absolute,
+    /// The path relative to another directory
+    RelativeTo(PathBuf),
+}
+
+/// Walks a directory and collects it into an owned [`Tree`].
+pub struct TreeBuilder {
+    root: PathBuf,
+    max_depth: Option<usize>,
+    show_hidden: bool,
+    sort: Sort,
+    gitignore: bool,
+    require_git: bool,
+    patterns: Patterns,
+    prune: bool,
+    du: bool,
+    allocated: bool,
+    follow: bool,
+    link_chains: bool,
+    broken_links_only: bool,
+    dirs_only: bool,
+    files_only: bool,
+    naming: Naming,
+    file_limit: Option<usize>,
+    max_children: Option<usize>,
+    filters: Vec<Filter>,
+}
+
+impl TreeBuilder {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        TreeBuilder {
+            root: root.as_ref().to_path_buf(),
+            max_depth: None,
+            show_hidden: false,
+            sort: Sort::default(),
+            gitignore: true,
+            require_git: true,
+            patterns: Patterns::default(),
+            prune: false,
+            du: false,
+            allocated: false,
+            follow: false,
+            link_chains: false,
+            broken_links_only: false,
+            dirs_only: false,
+            files_only: false,
+            naming: Naming::default(),
+            file_limit: None,
+            max_children: None,
+            filters: Vec::new(),
+        }
+    }
+
+    /// Sets the maximum depth to traverse, the root being at depth 0
+    pub fn max_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Include entries whose name starts with a dot
+    pub fn show_hidden(mut self, yes: bool) -> Self {
+        self.show_hidden = yes;
+        self
+    }
+
+    /// Sets how entries are ordered within each directory
+    pub fn sort(mut self, sort: Sort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Leave out entries matched by `.gitignore`, `.ignore`, `.git/info/exclude`
+    /// and git's `core.excludesFile`
+    pub fn gitignore(mut self, yes: bool) -> Self {
+        self.gitignore = yes;
+        self
+    }
+
+    /// Only apply git's ignore rules inside a git repository; `.ignore` files
+    /// are honored everywhere
+    pub fn require_git(mut self, yes: bool) -> Self {
+        self.require_git = yes;
+        self
+    }
+
+    /// Filters entries by include and exclude glob patterns
+    pub fn patterns(mut self, patterns: Patterns) -> Self {
+        self.patterns = patterns;
+        self
+    }
+
+    /// Drop directories that are empty once everything else has been filtered
+    pub fn prune(mut self, yes: bool) -> Self {
+        self.prune = yes;
+        self
+    }
+
+    /// Report the size of each directory as the total of everything below it,
+    /// counting hard-linked files only once.
+    ///
+    /// Entries left out by filters are not counted, but those below the
+    /// maximum depth are. Like pruning, this is only applied by
+    /// [`TreeBuilder::build`].
+    ///
+    /// Each tree is totalled on its own, so a file hard-linked into two
+    /// walked roots counts once in each of their reports.
+    pub fn du(mut self, yes: bool) -> Self {
+        self.du = yes;
+        self
+    }
+
+    /// Report the space allocated on disk instead of the apparent size, so
+    /// sparse files are not overstated
+    pub fn allocated(mut self, yes: bool) -> Self {
+        self.allocated = yes;
+        self
+    }
+
+    /// Descend into symlinked directories.
+    ///
+    /// A link leading back to one of its own ancestors, found by device and
+    /// inode, is listed without being entered and gets the error
+    /// `recursive, not followed`.
+    pub fn follow(mut self, yes: bool) -> Self {
+        self.follow = yes;
+        self
+    }
+
+    /// Resolve every symlink hop by hop into [`Node::link_chain`]
+    pub fn link_chains(mut self, yes: bool) -> Self {
+        self.link_chains = yes;
+        self
+    }
+
+    /// Only list symlinks whose target does not exist, and the directories
+    /// leading to them.
+    ///
+    /// Other directories are pruned, which only [`TreeBuilder::build`] does.
+    pub fn broken_links_only(mut self, yes: bool) -> Self {
+        self.broken_links_only = yes;
+        self
+    }
+
+    /// Only list directories and symlinks to them
+    pub fn dirs_only(mut self, yes: bool) -> Self {
+        self.dirs_only = yes;
+        self
+    }
+
+    /// List everything but directories as direct children of the root,
+    /// named by their path below it.
+    ///
+    /// [`TreeBuilder::build`] sorts this list as a whole, while
+    /// [`TreeBuilder::walk`] can only yield it sorted directory by directory.
+    pub fn files_only(mut self, yes: bool) -> Self {
+        self.files_only = yes;
+        self
+    }
+
+    /// Sets what the names of the nodes hold
+    pub fn naming(mut self, naming: Naming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Do not open directories below the root holding more than this many
+    /// entries, counted before any filtering; they get an error saying so instead
+    pub fn file_limit(mut self, limit: Option<usize>) -> Self {
+        self.file_limit = limit;
+        self
+    }
+
+    /// Keep only the first children of each directory, recording how many
+    /// were left out in [`Node::omitted`]. [`Tree::report`] still counts them.
+    /// Like pruning, this is only applied by [`TreeBuilder::build`].
+    pub fn max_children(mut self, max: Option<usize>) -> Self {
+        self.max_children = max;
+        self
+    }
+
+    /// Adds a predicate an entry must satisfy to be included.
+    ///
+    /// Rejecting a directory also skips everything below it. The root is
+    /// always included.
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Path) -> bool + 'static,
+    {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Walks the tree, passing every node to `visit` in pre-order as soon as
+    /// it is discovered, without keeping any of them.
+    ///
+    /// Nodes are passed without their children. Pruning needs the whole tree
+    /// and is only applied by [`TreeBuilder::build`].
+    ///
+    /// Entries that cannot be read are still visited when possible, with
+    /// [`Node::error`] set, the root included. Every problem met is also
+    /// returned; the walk only fails if `visit` does, or if the directory
+    /// given to [`Naming::RelativeTo`] cannot be resolved.
+    pub fn walk<F>(&self, visit: F) -> io::Result<Vec<WalkError>>
+    where
+        F: FnMut(Node) -> io::Result<()>,
+    {
+        self.walk_to(self.max_depth, visit)
+    }
+
+    fn walk_to<F>(&self, max_depth: Option<usize>, mut visit: F) -> io::Result<Vec<WalkError>>
+    where
+        F: FnMut(Node) -> io::Result<()>,
+    {
+        // Where to start absolute names, and what to make them relative to
+        let (absolute_root, relative_to) = match &self.naming {
+            Naming::FileName | Naming::Full => (None, None),
+            // A root that cannot be resolved cannot be read either, and keeps its name
+            Naming::Absolute => (fs::canonicalize(&self.root).ok(), None),
+            Naming::RelativeTo(dir) => {
+                let dir = fs::canonicalize(dir).map_err(|err| {
+                    io::Error::new(err.kind(), format!("{}: {}", dir.display(), err))
+                })?;
+                (fs::canonicalize(&self.root).ok(), Some(dir))
+            }
+        };
+
+        let mut walker = WalkDir::new(&self.root).follow_links(self.follow);
+        if let Some(max_depth) = max_depth {
+            walker = walker.max_depth(max_depth);
+        }
+        if self.sort.is_sorted() {
+            walker = walker.sort_by(self.sort.comparator());
+        }
+
+        let ignore = RefCell::new(
+            self.gitignore
+                .then(|| IgnoreFilter::new(&self.root, self.require_git)),
+        );
+
+        // Depth of a directory matched by the include patterns, whose contents are all listed
+        let matched_dir: Cell<Option<usize>> = Cell::new(None);
+
+        // Shared with the entries walkdir could not follow, which it reports as errors
+        let keep = |path: &Path, depth: usize, is_dir: bool| {
+            if matched_dir.get().is_some_and(|matched| depth <= matched) {
+                matched_dir.set(None);
+            }
+            // Called for the root too, so the filter picks up its ignore files
+            let ignored = match &mut *ignore.borrow_mut() {
+                Some(filter) => filter.is_ignored(path, depth, is_dir),
+                None => false,
+            };
+            if depth == 0 {
+                // Always include the root directory
+                return true;
+            }
+            let name = path.file_name().unwrap_or_default();
+            if ignored || (!self.show_hidden && is_hidden(name)) {
+                return false;
+            }
+            // Like GNU tree, links to directories are kept without being followed
+            if self.dirs_only && !is_dir && !fs::metadata(path).is_ok_and(|m| m.is_dir()) {
+                return false;
+            }
+            if self.broken_links_only && !is_dir && !is_broken(path) {
+                return false;
+            }
+            let mut matched = matched_dir.get();
+            let keep = self.filters.iter().all(|f| f(path))
+                && self.matches_patterns(path, name, is_dir, depth, &mut matched);
+            matched_dir.set(matched);
+            keep
+        };
+
+        let mut walker = walker
+            .into_iter()
+            .filter_entry(|e| keep(e.path(), e.depth(), e.file_type().is_dir()));
+
+        let mut visit = |mut node: Node| {
+            if self.files_only && node.depth > 0 {
+                if node.is_dir() {
+                    // Still walked, just not listed
+                    return Ok(());
+                }
+                let relative = node.path.strip_prefix(&self.root).unwrap_or(&node.path);
+                node.name = relative.to_string_lossy().into_owned();
+                node.depth = 1;
+            }
+            if self.naming == Naming::Full {
+                node.name = node.path.to_string_lossy().into_owned();
+            } else if let Some(root) = &absolute_root {
+                let relative = node.path.strip_prefix(&self.root).unwrap_or(&node.path);
+                let mut path = root.clone();
+                if !relative.as_os_str().is_empty() {
+                    path.push(relative);
+                }
+                if let Some(base) = &relative_to {
+                    path = relative_path(&path, base);
+                }
+                node.name = path.to_string_lossy().into_owned();
+            }
+            visit(node)
+        };
+
+        // A directory is only opened after it has been yielded, so every node
+        // is held back until the next item shows whether that failed
+        let mut pending: Option<Node> = None;
+        let mut errors = Vec::new();
+
+        while let Some(entry) = walker.next() {
+            match entry {
+                Ok(entry) => {
+                    if let Some(node) = pending.take() {
+                        visit(node)?;
+                    }
+                    let mut node = self.make_node(&entry, &mut errors);
+                    // The root is always opened, being what was asked for
+                    let limited = node.is_dir() && node.depth > 0 && max_depth != Some(node.depth);
+                    if let Some(limit) = self.file_limit.filter(|_| limited) {
+                        let count = fs::read_dir(&node.path).map_or(0, |dir| dir.count());
+                        if count > limit {
+                            node.error =
+                                Some(format!("{} entries exceeds filelimit, not opened", count));
+                            walker.skip_current_dir();
+                        }
+                    }
+                    pending = Some(node);
+                }
+                Err(err) => {
+                    if let Some(node) = self.unfollowed_node(&err) {
+                        if keep(&node.path, node.depth, node.is_dir()) {
+                            if let Some(node) = pending.take() {
+                                visit(node)?;
+                            }
+                            pending = Some(node);
+                        }
+                        continue;
+                    }
+                    let Some(node) = pending.as_mut() else {
+                        // The root itself could not be read, so it is all there is to show
+                        pending = Some(self.unreadable_root());
+                        errors.push(walk_error(&err));
+                        continue;
+                    };
+                    if node.is_dir() && err.path() == Some(node.path.as_path()) {
+                        node.error = Some("error opening dir".to_string());
+                    }
+                    errors.push(walk_error(&err));
+                }
+            }
+        }
+
+        if let Some(node) = pending {
+            visit(node)?;
+        }
+        Ok(errors)
+    }
+
+    /// Walks the whole tree and collects it in memory.
+    pub fn build(&self) -> io::Result<Tree> {
+        // Nodes whose children are still being collected, from the root down
+        let mut stack: Vec<Node> = Vec::new();
+
+        // Totals include what lies below the maximum depth, which is cut off afterwards
+        let max_depth = if self.du { None } else { self.max_depth };
+        let errors = self.walk_to(max_depth, |node| {
+            close_until(&mut stack, node.depth);
+            stack.push(node);
+            Ok(())
+        })?;
+        close_until(&mut stack, 1);
+
+        let mut root = stack.pop().expect("walk visits the root first");
+        if self.prune || self.broken_links_only {
+            self.prune_empty_dirs(&mut root);
+        }
+        if self.du {
+            total_size(&mut root, self.allocated, &mut HashSet::new());
+            if self.sort.by == SortBy::Size {
+                // The walk could only sort directories by their own size
+                let mut stack = vec![&mut root];
+                while let Some(node) = stack.pop() {
+                    node.children.sort_by(|a, b| self.sort.compare_nodes(a, b));
+                    stack.extend(node.children.iter_mut());
+                }
+            }
+            if let Some(max_depth) = self.max_depth {
+                let mut stack = vec![&mut root];
+                while let Some(node) = stack.pop() {
+                    if node.depth == max_depth {
+                        node.children.clear();
+                    }
+                    stack.extend(node.children.iter_mut());
+                }
+            }
+        } else if self.allocated {
+            let mut stack = vec![&mut root];
+            while let Some(node) = stack.pop() {
+                if let Some(metadata) = &mut node.metadata {
+                    metadata.size = metadata.allocated;
+                }
+                stack.extend(node.children.iter_mut());
+            }
+        }
+        if self.files_only && self.sort.is_sorted() {
+            // The walk could only sort the files of each directory among themselves
+            root.children.sort_by(|a, b| self.sort.compare_nodes(a, b));
+        }
+        // Counted first, so the totals still cover what is left out below
+        let report = self.report(&root, errors.len());
+        if let Some(max) = self.max_children {
+            omit_children(&mut root, max);
+        }
+        Ok(Tree {
+            root,
+            errors,
+            report,
+        })
+    }
+
+    /// Counts the entries below `root`.
+    fn report(&self, root: &Node, errors: usize) -> Report {
+        let mut report = Report {
+            errors,
+            ..Report::default()
+        };
+        let mut seen = HashSet::new();
+        for node in root.iter().skip(1) {
+            report.add(node);
+            // Like the directory totals, the total counts a hard-linked file once
+            if self.du && !first_sight(node, &mut seen) {
+                report.bytes -= node.size().unwrap_or(0);
+            }
+        }
+        report
+    }
+
+    /// Applies the include and exclude patterns, tracking in `matched_dir`
+    /// the directory below which include patterns are disabled.
+    fn matches_patterns(
+        &self,
+        path: &Path,
+        name: &OsStr,
+        is_dir: bool,
+        depth: usize,
+        matched_dir: &mut Option<usize>,
+    ) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        if self.patterns.is_excluded(name, relative) {
+            return false;
+        }
+        if matched_dir.is_some() || !self.patterns.has_include() {
+            return true;
+        }
+        if is_dir {
+            // Directories are kept so matching files below them can be found
+            if self.patterns.match_dirs && self.patterns.is_included(name, relative) {
+                *matched_dir = Some(depth);
+            }
+            true
+        } else {
+            self.patterns.is_included(name, relative)
+        }
+    }
+
+    /// Creates the node for `entry`, recording in `errors` if its metadata cannot be read.
+    fn make_node(&self, entry: &DirEntry, errors: &mut Vec<WalkError>) -> Node {
+        let kind = node_kind(entry.file_type());
+
+        let link_target = if entry.path_is_symlink() {
+            entry.path().read_link().ok()
+        } else {
+            None
+        };
+
+        let mut error = None;
+        let metadata = match entry.metadata() {
+            Ok(metadata) => Some(read_metadata(&metadata)),
+            Err(err) => {
+                error = Some("error reading metadata".to_string());
+                errors.push(walk_error(&err));
+                None
+            }
+        };
+
+        // Like GNU tree, the root is shown as the path it was given by
+        let name = if entry.depth() == 0 {
+            entry.path().to_string_lossy().into_owned()
+        } else {
+            entry.file_name().to_string_lossy().into_owned()
+        };
+
+        let mut node = Node {
+            name,
+            path: entry.path().to_path_buf(),
+            depth: entry.depth(),
+            kind,
+            link_target,
+            link_chain: Vec::new(),
+            broken: kind == NodeKind::Symlink && fs::metadata(entry.path()).is_err(),
+            metadata,
+            error,
+            children: Vec::new(),
+            omitted: None,
+        };
+        if self.link_chains {
+            node.link_chain = link_chain(&node);
+        }
+        node
+    }
+
+    /// Creates the node for a symlink walkdir refused to follow, because it
+    /// leads back to one of its ancestors or nowhere at all.
+    ///
+    /// Returns `None` for any other error.
+    fn unfollowed_node(&self, err: &walkdir::Error) -> Option<Node> {
+        let path = err.path().filter(|_| self.follow && err.depth() > 0)?;
+        let link = fs::symlink_metadata(path).ok()?;
+        if !link.file_type().is_symlink() {
+            return None;
+        }
+
+        let target = fs::metadata(path);
+        let (kind, metadata, error) = if err.loop_ancestor().is_some() {
+            let metadata = target.ok().map(|metadata| read_metadata(&metadata));
+            let error = Some("recursive, not followed".to_string());
+            (NodeKind::Directory, metadata, error)
+        } else if target.is_err() {
+            (NodeKind::Symlink, Some(read_metadata(&link)), None)
+        } else {
+            return None;
+        };
+
+        let mut node = Node {
+            name: path.file_name()?.to_string_lossy().into_owned(),
+            path: path.to_path_buf(),
+            depth: err.depth(),
+            kind,
+            link_target: path.read_link().ok(),
+            link_chain: Vec::new(),
+            broken: kind == NodeKind::Symlink,
+            metadata,
+            error,
+            children: Vec::new(),
+            omitted: None,
+        };
+        if self.link_chains {
+            node.link_chain = link_chain(&node);
+        }
+        Some(node)
+    }
+
+    /// Creates the node for a root that could not be read at all, e.g. one
+    /// that does not exist.
+    fn unreadable_root(&self) -> Node {
+        let kind = fs::symlink_metadata(&self.root).map_or(NodeKind::Directory, |metadata| {
+            node_kind(metadata.file_type())
+        });
+        Node {
+            name: self.root.to_string_lossy().into_owned(),
+            path: self.root.clone(),
+            depth: 0,
+            kind,
+            link_target: self.root.read_link().ok(),
+            link_chain: Vec::new(),
+            broken: is_broken(&self.root),
+            metadata: None,
+            error: Some("error opening dir".to_string()),
+            children: Vec::new(),
+            omitted: None,
+        }
+    }
+
+    /// Removes directories left without children, except those at the
+    /// maximum depth whose contents were never read.
+    fn prune_empty_dirs(&self, node: &mut Node) {
+        node.children.retain_mut(|child| {
+            if !child.is_dir() || self.max_depth == Some(child.depth) {
+                return true;
+            }
+            self.prune_empty_dirs(child);
+            !child.children.is_empty()
+        });
+    }
+}
+
+/// Replaces the size of every directory below `node` with the total of its
+/// contents and returns the total for `node`.
+///
+/// Files with several hard links are only counted the first time `seen`
+/// meets them.
+fn total_size(node: &mut Node, allocated: bool, seen: &mut HashSet<(u64, u64)>) -> u64 {
+    let is_dir = node.is_dir();
+    let counted = first_sight(node, seen);
+    let mut total = 0;
+    if let Some(metadata) = &mut node.metadata {
+        if allocated {
+            metadata.size = metadata.allocated;
+        }
+        if counted {
+            total = metadata.size;
+        }
+    }
+
+    for child in &mut node.children {
+        total += total_size(child, allocated, seen);
+    }
+    if let Some(metadata) = node.metadata.as_mut().filter(|_| is_dir) {
+        metadata.size = total;
+    }
+    total
+}
+
+/// Whether `node` is met for the first time, recording files with several
+/// hard links in `seen` so they are only counted once.
+fn first_sight(node: &Node, seen: &mut HashSet<(u64, u64)>) -> bool {
+    match node.metadata.as_ref() {
+        Some(metadata) if metadata.links > 1 && !node.is_dir() => {
+            metadata.file_id.is_none_or(|id| seen.insert(id))
+        }
+        _ => true,
+    }
+}
+
+/// Keeps the first `max` children of every directory from `node` down,
+/// recording what the others held.
+fn omit_children(node: &mut Node, max: usize) {
+    if node.children.len() > max {
+        let rest = node.children.split_off(max);
+        let bytes = rest
+            .iter()
+            .flat_map(Node::iter)
+            .filter(|node| !node.is_dir())
+            .filter_map(Node::size)
+            .sum();
+        node.omitted = Some(Omitted {
+            entries: rest.len(),
+            bytes,
+        });
+    }
+    for child in &mut node.children {
+        omit_children(child, max);
+    }
+}
+
+/// Pops finished nodes into their parent until the stack holds `depth` nodes.
+///
+/// The root is never popped, so it can be returned once the walk is over.
+fn close_until(stack: &mut Vec<Node>, depth: usize) {
+    while stack.len() > depth.max(1) {
+        let node = stack.pop().unwrap();
+        stack.last_mut().unwrap().children.push(node);
+    }
+}
+
+/// The way from the directory `base` to `path`, both absolute and canonical.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path: Vec<_> = path.components().collect();
+    let base: Vec<_> = base.components().collect();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    relative.extend(&path[common..]);
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
+
+/// Every target of the symlink `node` after the first, resolving each in turn.
+fn link_chain(node: &Node) -> Vec<PathBuf> {
+    let mut chain = Vec::new();
+    let Some(first) = &node.link_target else {
+        return chain;
+    };
+    let mut current = resolve(&node.path, first);
+    // Linux gives up with ELOOP after as many hops
+    while chain.len() < 40 {
+        let Ok(target) = current.read_link() else {
+            break;
+        };
+        current = resolve(&current, &target);
+        chain.push(target);
+    }
+    chain
+}
+
+/// Where `target` points to, as read from the symlink at `link`.
+fn resolve(link: &Path, target: &Path) -> PathBuf {
+    match link.parent() {
+        Some(parent) => parent.join(target),
+        None => target.to_path_buf(),
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn node_kind(file_type: fs::FileType) -> NodeKind {
+    use std::os::unix::fs::FileTypeExt;
+
+    if file_type.is_dir() {
+        NodeKind::Directory
+    } else if file_type.is_symlink() {
+        NodeKind::Symlink
+    } else if file_type.is_file() {
+        NodeKind::File
+    } else if file_type.is_fifo() {
+        NodeKind::Fifo
+    } else if file_type.is_socket() {
+        NodeKind::Socket
+    } else if file_type.is_block_device() {
+        NodeKind::BlockDevice
+    } else if file_type.is_char_device() {
+        NodeKind::CharDevice
+    } else {
+        NodeKind::Other
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn node_kind(file_type: fs::FileType) -> NodeKind {
+    if file_type.is_dir() {
+        NodeKind::Directory
+    } else if file_type.is_symlink() {
+        NodeKind::Symlink
+    } else if file_type.is_file() {
+        NodeKind::File
+    } else {
+        NodeKind::Other
+    }
+}
+
+fn walk_error(err: &walkdir::Error) -> WalkError {
+    WalkError {
+        path: err.path().map(Path::to_path_buf),
+        message: match err.io_error() {
+            Some(io_error) => io_error.to_string(),
+            None => err.to_string(),
+        },
+    }
+}
+
+#[cfg(unix)]
+fn read_metadata(metadata: &fs::Metadata) -> Metadata {
+    use std::os::unix::fs::MetadataExt;
+
+    Metadata {
+        size: metadata.len(),
+        // st_blocks is always in units of 512 bytes
+        allocated: metadata.blocks() * 512,
+        modified: metadata.modified().ok(),
+        accessed: metadata.accessed().ok(),
+        changed: change_time(metadata),
+        created: metadata.created().ok(),
+        links: metadata.nlink(),
+        file_id: Some((metadata.dev(), metadata.ino())),
+        mode: Some(metadata.mode()),
+        uid: Some(metadata.uid()),
+        gid: Some(metadata.gid()),
+    }
+}
+
+#[cfg(not(unix))]
+fn read_metadata(metadata: &fs::Metadata) -> Metadata {
+    Metadata {
+        size: metadata.len(),
+        allocated: metadata.len(),
+        modified: metadata.modified().ok(),
+        accessed: metadata.accessed().ok(),
+        changed: change_time(metadata),
+        created: metadata.created().ok(),
+        links: 1,
+        file_id: None,
+        mode: None,
+        uid: None,
+        gid: None,
+    }
+}
+
+fn is_broken(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|link| link.file_type().is_symlink())
+        && fs::metadata(path).is_err()
+}
+
+// Helper function to determine if a file is hidden
+fn is_hidden(name: &OsStr) -> bool {
+    name.to_str().map(|s| s.starts_with('.')).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    /// The node at `path` below the root of `tree`.
+    fn find<'a>(tree: &'a Tree, path: &Path) -> &'a Node {
+        tree.iter().find(|node| node.path == path).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn du_counts_hard_links_once() {
+        use std::os::unix::fs::MetadataExt;
+
+        let fixture = Fixture::new("hardlinks", &[("a/f", &"x".repeat(3000)), ("b/h", "y")]);
+        let root = fixture.path();
+        fs::hard_link(root.join("a/f"), root.join("b/g")).unwrap();
+        let own = |path: &str| fs::metadata(root.join(path)).unwrap();
+
+        let tree = TreeBuilder::new(root).du(true).build().unwrap();
+        // `a` is walked first, so `b/g` is the link left uncounted
+        assert_eq!(
+            find(&tree, &root.join("a")).size(),
+            Some(own("a").len() + 3000)
+        );
+        assert_eq!(
+            find(&tree, &root.join("b")).size(),
+            Some(own("b").len() + 1)
+        );
+        assert_eq!(find(&tree, &root.join("b/g")).size(), Some(3000));
+        assert_eq!(tree.report.bytes, 3001);
+        assert_eq!(tree.report.files, 3);
+
+        // Without --du every file is listed and counted with its own size
+        let tree = TreeBuilder::new(root).build().unwrap();
+        assert_eq!(find(&tree, &root.join("a")).size(), Some(own("a").len()));
+        assert_eq!(tree.report.bytes, 6001);
+
+        let allocated = |path: &str| own(path).blocks() * 512;
+        let tree = TreeBuilder::new(root)
+            .du(true)
+            .allocated(true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            find(&tree, &root.join("a")).size(),
+            Some(allocated("a") + allocated("a/f"))
+        );
+        assert_eq!(
+            find(&tree, &root.join("b")).size(),
+            Some(allocated("b") + allocated("b/h"))
+        );
+        assert_eq!(tree.report.bytes, allocated("a/f") + allocated("b/h"));
+    }
+}
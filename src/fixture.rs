@@ -0,0 +1,34 @@
+//! Directories built on disk for the tests.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// A temporary directory holding the given files, removed when dropped.
+pub(crate) struct Fixture(PathBuf);
+
+impl Fixture {
+    /// Creates the files below a directory unique to `name`, with their
+    /// parent directories.
+    pub(crate) fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("tree-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        Fixture(dir)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
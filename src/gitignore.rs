@@ -0,0 +1,246 @@
+//! Filtering of entries by `.gitignore`, `.ignore` and git's exclude files.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Ignore files found in one directory.
+struct Level {
+    gitignore: Gitignore,
+    ignore: Gitignore,
+    /// `.git/info/exclude`, when the directory is the top of a repository
+    exclude: Option<Gitignore>,
+}
+
+impl Level {
+    fn read(dir: &Path) -> Self {
+        let git_dir = dir.join(".git");
+        let exclude = if git_dir.exists() {
+            let mut builder = GitignoreBuilder::new(dir);
+            let path = git_dir.join("info").join("exclude");
+            if path.is_file() {
+                builder.add(path);
+            }
+            Some(builder.build().unwrap_or_else(|_| Gitignore::empty()))
+        } else {
+            None
+        };
+
+        Level {
+            gitignore: read_ignore_file(&dir.join(".gitignore")),
+            ignore: read_ignore_file(&dir.join(".ignore")),
+            exclude,
+        }
+    }
+}
+
+fn read_ignore_file(path: &Path) -> Gitignore {
+    if path.is_file() {
+        // Invalid globs are skipped; the valid ones still apply
+        Gitignore::new(path).0
+    } else {
+        Gitignore::empty()
+    }
+}
+
+/// Decides whether walked entries are ignored, keeping the ignore files of
+/// the directories above the current entry on a stack.
+///
+/// Entries must be passed in the pre-order they are walked in, and ignored
+/// directories must not be descended into.
+pub(crate) struct IgnoreFilter {
+    root: PathBuf,
+    absolute_root: PathBuf,
+    /// Only apply git's rules inside a git repository
+    require_git: bool,
+    /// Levels for the directories from the repository top down to the root's parent
+    parents: usize,
+    levels: Vec<Level>,
+    global: Gitignore,
+}
+
+impl IgnoreFilter {
+    pub(crate) fn new(root: &Path, require_git: bool) -> Self {
+        let absolute_root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+
+        // Rules of the enclosing repository apply even when walking a subdirectory
+        let mut levels = Vec::new();
+        if let Some(top) = absolute_root
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.join(".git").exists())
+        {
+            let mut parents: Vec<&Path> = absolute_root
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| *dir != top)
+                .collect();
+            parents.push(top);
+            levels.extend(parents.into_iter().rev().map(Level::read));
+        }
+
+        IgnoreFilter {
+            root: root.to_path_buf(),
+            parents: levels.len(),
+            levels,
+            global: GitignoreBuilder::new(&absolute_root).build_global().0,
+            absolute_root,
+            require_git,
+        }
+    }
+
+    /// Returns true if the entry at `path` and `depth` should be left out.
+    pub(crate) fn is_ignored(&mut self, path: &Path, depth: usize, is_dir: bool) -> bool {
+        self.levels.truncate(self.parents + depth);
+
+        let ignored = depth > 0 && self.matched(path, is_dir);
+        if is_dir && !ignored {
+            let dir = self.absolute(path);
+            self.levels.push(Level::read(&dir));
+        }
+        ignored
+    }
+
+    fn matched(&self, path: &Path, is_dir: bool) -> bool {
+        let path = self.absolute(path);
+        let in_repo = self.levels.iter().any(|level| level.exclude.is_some());
+
+        // The deepest matching file decides, `.ignore` taking precedence over git's rules
+        let mut found = first_match(self.levels.iter().rev().map(|l| &l.ignore), &path, is_dir);
+        if !in_repo && self.require_git {
+            return found.is_ignore();
+        }
+        if found.is_none() {
+            found = first_match(
+                self.levels.iter().rev().map(|l| &l.gitignore),
+                &path,
+                is_dir,
+            );
+        }
+        if found.is_none() {
+            found = first_match(
+                self.levels.iter().rev().filter_map(|l| l.exclude.as_ref()),
+                &path,
+                is_dir,
+            );
+        }
+        if found.is_none() {
+            found = self.global.matched(&path, is_dir).map(|_| ());
+        }
+        found.is_ignore()
+    }
+
+    fn absolute(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.root) {
+            Ok(relative) if relative.as_os_str().is_empty() => self.absolute_root.clone(),
+            Ok(relative) => self.absolute_root.join(relative),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+}
+
+fn first_match<'a, I>(matchers: I, path: &Path, is_dir: bool) -> Match<()>
+where
+    I: Iterator<Item = &'a Gitignore>,
+{
+    matchers
+        .map(|gitignore| gitignore.matched(path, is_dir).map(|_| ()))
+        .find(|found| !found.is_none())
+        .unwrap_or(Match::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixture::Fixture;
+    use crate::TreeBuilder;
+
+    /// Paths listed below `dir` of the fixture, relative to it.
+    fn listed(fixture: &Fixture, dir: &str, require_git: bool) -> Vec<String> {
+        let root = fixture.path().join(dir);
+        let tree = TreeBuilder::new(&root)
+            .require_git(require_git)
+            .build()
+            .unwrap();
+        tree.iter()
+            .skip(1)
+            .map(|node| {
+                node.path
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested_negation_overrides_parent() {
+        let fixture = Fixture::new(
+            "negation",
+            &[
+                (".git/HEAD", ""),
+                (".gitignore", "*.log\n"),
+                ("a.log", ""),
+                ("sub/.gitignore", "!keep.log\n"),
+                ("sub/keep.log", ""),
+                ("sub/deeper/drop.log", ""),
+                ("sub/deeper/keep.log", ""),
+            ],
+        );
+        assert_eq!(
+            listed(&fixture, "", true),
+            ["sub", "sub/deeper", "sub/deeper/keep.log", "sub/keep.log"]
+        );
+    }
+
+    #[test]
+    fn ignore_file_beats_gitignore() {
+        let fixture = Fixture::new(
+            "precedence",
+            &[
+                (".git/HEAD", ""),
+                (".gitignore", "build/\n"),
+                (".ignore", "!build/\nnotes.txt\n"),
+                ("build/out", ""),
+                ("notes.txt", ""),
+            ],
+        );
+        assert_eq!(listed(&fixture, "", true), ["build", "build/out"]);
+    }
+
+    #[test]
+    fn info_exclude_and_enclosing_repository() {
+        let fixture = Fixture::new(
+            "exclude",
+            &[
+                (".git/info/exclude", "secret\n"),
+                (".gitignore", "*.o\n"),
+                ("secret", ""),
+                ("public", ""),
+                ("sub/main.o", ""),
+                ("sub/secret", ""),
+                ("sub/main.c", ""),
+            ],
+        );
+        assert_eq!(listed(&fixture, "", true), ["public", "sub", "sub/main.c"]);
+        // Walking a subdirectory still applies the rules of the repository above it
+        assert_eq!(listed(&fixture, "sub", true), ["main.c"]);
+    }
+
+    #[test]
+    fn git_rules_need_a_repository_unless_asked() {
+        let fixture = Fixture::new(
+            "outside",
+            &[
+                (".gitignore", "*.log\n"),
+                (".ignore", "*.bak\n"),
+                ("a.bak", ""),
+                ("a.log", ""),
+                ("a.txt", ""),
+            ],
+        );
+        assert_eq!(listed(&fixture, "", true), ["a.log", "a.txt"]);
+        assert_eq!(listed(&fixture, "", false), ["a.txt"]);
+    }
+}
@@ -0,0 +1,24 @@
+//! Walk a directory into an owned [`Tree`] and render it like the Unix `tree` command.
+//!
+//! ```no_run
+//! use tree::render::{Render, TextRenderer};
+//! use tree::TreeBuilder;
+//!
+//! let tree = TreeBuilder::new(".").max_depth(Some(2)).build()?;
+//! TextRenderer::default().render(&tree, &mut std::io::stdout())?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+pub mod builder;
+#[cfg(test)]
+mod fixture;
+mod gitignore;
+pub mod pattern;
+pub mod render;
+pub mod sort;
+pub mod tree;
+
+pub use builder::{Naming, TreeBuilder};
+pub use pattern::Patterns;
+pub use sort::{DirOrder, Sort, SortBy};
+pub use tree::{Metadata, Node, NodeKind, Omitted, Report, Tree, WalkError};
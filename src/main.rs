@@ -1,163 +1,421 @@
-use chrono::{DateTime, Local};
-use clap::Parser;
-use colored::*;
+use clap::{CommandFactory, Parser};
 use std::fs::File;
-use std::io::{self, Write};
-use walkdir::WalkDir;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tree::render::{
+    ColorMode, Columns, Format, HtmlRenderer, JsonLinesRenderer, JsonRenderer, LsColors, Render,
+    SizeFormat, Template, TextRenderer, TimeColumn, TimeField, TimeStyle, XmlRenderer,
+};
+use tree::{DirOrder, Naming, Patterns, Report, Sort, SortBy, TreeBuilder, WalkError};
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, disable_help_flag = true)]
 struct Args {
-    /// Sets the root directory to display
-    #[arg(short, long)]
-    directory: Option<String>,
+    /// Directories to display, one tree each
+    #[arg(value_name = "DIRECTORY", default_value = ".")]
+    roots: Vec<String>,
+
+    /// Print the path of each entry, starting with the root as given
+    #[arg(short = 'f', long, conflicts_with_all = ["absolute", "relative_to"])]
+    full_path: bool,
+
+    /// Print the canonical absolute path of each entry
+    #[arg(long, conflicts_with = "relative_to")]
+    absolute: bool,
+
+    /// Print the path of each entry relative to this directory
+    #[arg(long, value_name = "DIR")]
+    relative_to: Option<PathBuf>,
+
+    /// Do not open directories holding more than this many entries
+    #[arg(long, value_name = "N")]
+    filelimit: Option<usize>,
+
+    /// Show at most this many entries per directory, summarizing the rest
+    #[arg(long, value_name = "N")]
+    max_children: Option<usize>,
+
+    /// Show directories holding only another directory on one line, like a/b/c/
+    #[arg(long)]
+    compact_chains: bool,
+
+    /// List directories only
+    #[arg(short = 'd', long, conflicts_with = "files_only")]
+    dirs_only: bool,
+
+    /// List everything but directories in one flat list of paths
+    #[arg(long)]
+    files_only: bool,
 
     /// Sets the maximum depth to traverse
-    #[arg(short, long)]
+    #[arg(short = 'L', long)]
     level: Option<usize>,
 
+    /// Descend into symlinked directories, skipping those that loop
+    #[arg(short = 'l', long)]
+    follow: bool,
+
+    /// List only symlinks whose target does not exist
+    #[arg(long)]
+    broken_links_only: bool,
+
+    /// Show every hop of chained symlinks and whether the last one exists
+    #[arg(long)]
+    link_chain: bool,
+
     /// Show hidden files
     #[arg(short, long, action = clap::ArgAction::SetTrue)]
     all: bool,
 
-    /// Disable colors
+    /// Honor .gitignore files even outside of a git repository
+    #[arg(long, conflicts_with = "no_gitignore")]
+    gitignore: bool,
+
+    /// Show entries matched by .gitignore, .ignore and git's exclude files
+    #[arg(long)]
+    no_gitignore: bool,
+
+    /// List only files matching the pattern; alternatives are separated by '|'
+    #[arg(short = 'P', long, value_name = "PATTERN")]
+    pattern: Vec<String>,
+
+    /// Do not list entries matching the pattern; alternatives are separated by '|'
+    #[arg(short = 'I', long, value_name = "PATTERN")]
+    ignore: Vec<String>,
+
+    /// Apply --pattern to directory names too, listing all of a matching directory
+    #[arg(long)]
+    matchdirs: bool,
+
+    /// Match patterns case-insensitively
+    #[arg(long)]
+    ignore_case: bool,
+
+    /// Do not show directories left empty by filtering
+    #[arg(long)]
+    prune: bool,
+
+    /// Sort the entries of each directory by this key
+    #[arg(long, value_enum, default_value_t = SortBy::Name)]
+    sort: SortBy,
+
+    /// Reverse the sort order
     #[arg(short, long)]
+    reverse: bool,
+
+    /// List directories before files
+    #[arg(long, conflicts_with = "filesfirst")]
+    dirsfirst: bool,
+
+    /// List files before directories
+    #[arg(long)]
+    filesfirst: bool,
+
+    /// Print permissions like drwxr-xr-x
+    #[arg(short = 'p', long)]
+    permissions: bool,
+
+    /// Print permissions in octal, like 0755
+    #[arg(long)]
+    octal: bool,
+
+    /// Print the name of the owning user
+    #[arg(short = 'u', long)]
+    user: bool,
+
+    /// Print the name of the owning group
+    #[arg(short = 'g', long)]
+    group: bool,
+
+    /// Print sizes in bytes (the default)
+    #[arg(short = 's', long = "size")]
+    bytes: bool,
+
+    /// Print sizes in powers of 1024, like 4.0K or 12M
+    #[arg(short = 'h', long = "human", conflicts_with = "si")]
+    human: bool,
+
+    /// Print sizes in powers of 1000, like 4.1k or 13M
+    #[arg(long)]
+    si: bool,
+
+    /// Do not print sizes
+    #[arg(long, conflicts_with_all = ["bytes", "human", "si"])]
+    no_size: bool,
+
+    /// Which timestamp to print
+    #[arg(long, value_enum, value_name = "FIELD", default_value_t = TimeField::Mtime)]
+    time: TimeField,
+
+    /// How to print timestamps
+    #[arg(long, value_enum, value_name = "STYLE", default_value_t = TimeStyle::Default)]
+    time_style: TimeStyle,
+
+    /// Print timestamps with a strftime format, like '%b %e %H:%M'
+    #[arg(long, value_name = "FORMAT", conflicts_with = "time_style", value_parser = strftime)]
+    timefmt: Option<String>,
+
+    /// Print timestamps in UTC instead of local time
+    #[arg(long)]
+    utc: bool,
+
+    /// Do not print timestamps
+    #[arg(long, conflicts_with_all = ["time", "time_style", "timefmt", "utc"])]
+    no_time: bool,
+
+    /// Write each text line from a template, like '{prefix}{name} [{size:h} {mtime:%F}]'
+    #[arg(long, value_name = "TEMPLATE", value_parser = Template::parse)]
+    template: Option<Template>,
+
+    /// Report each directory's size as the total of its contents, totalling each root on its own
+    #[arg(long)]
+    du: bool,
+
+    /// Report the space allocated on disk instead of the apparent size
+    #[arg(long)]
+    allocated: bool,
+
+    /// Do not print the closing report with the counts
+    #[arg(long)]
+    noreport: bool,
+
+    /// Exit with status 2 if some entries could not be read
+    #[arg(long)]
+    error_exit: bool,
+
+    /// Output format; jsonl streams entries as found, so it cannot prune,
+    /// total or limit them
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Prefix for the file links of the HTML output
+    #[arg(long, value_name = "URL")]
+    base_href: Option<String>,
+
+    /// When to color names
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorMode::Auto)]
+    color: ColorMode,
+
+    /// Disable colors, the same as --color=never
+    #[arg(short, long, conflicts_with = "color")]
     no_color: bool,
 
     /// Output to a file instead of stdout
     #[arg(short, long)]
     output: Option<String>,
+
+    /// Print help
+    #[arg(long, action = clap::ArgAction::Help)]
+    help: Option<bool>,
 }
 
-fn main() -> io::Result<()> {
-    let args: Args = Args::parse();
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let error_exit = args.error_exit;
 
-    // Set the target directory: use the provided directory or default to "."
-    let target_dir = args.directory.unwrap_or_else(|| ".".to_string());
-    let show_hidden: bool = args.all;
+    match run(args) {
+        Ok(errors) if errors.is_empty() => ExitCode::SUCCESS,
+        Ok(errors) => {
+            for error in &errors {
+                eprintln!("tree: {}", error);
+            }
+            let plural = if errors.len() == 1 { "" } else { "s" };
+            eprintln!(
+                "tree: the listing is incomplete, {} error{} while walking",
+                errors.len(),
+                plural
+            );
+            if error_exit {
+                ExitCode::from(2)
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        // The reader went away, e.g. `tree --format jsonl | head`
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("tree: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Checks a `--timefmt` value up front, as a bad one would only fail while printing.
+fn strftime(format: &str) -> Result<String, String> {
+    TimeColumn::check_strftime(format).map(|()| format.to_string())
+}
+
+/// Prints the tree and returns the problems met while walking it.
+fn run(args: Args) -> io::Result<Vec<WalkError>> {
+    let mut patterns = match Patterns::new(&args.pattern, &args.ignore, args.ignore_case) {
+        Ok(patterns) => patterns,
+        Err(err) => Args::command()
+            .error(clap::error::ErrorKind::ValueValidation, err)
+            .exit(),
+    };
+    patterns.match_dirs = args.matchdirs;
+
+    if args.template.is_some() && args.format != Format::Text {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--template only shapes the lines of --format text",
+            )
+            .exit()
+    }
+
+    if args.format == Format::Jsonl {
+        let whole_tree = [
+            ("--prune", args.prune),
+            ("--du", args.du),
+            ("--max-children", args.max_children.is_some()),
+            ("--broken-links-only", args.broken_links_only),
+        ];
+        if let Some((flag, _)) = whole_tree.iter().find(|(_, set)| *set) {
+            Args::command()
+                .error(
+                    clap::error::ErrorKind::ArgumentConflict,
+                    format!(
+                        "{} needs the whole tree, which --format jsonl never holds",
+                        flag
+                    ),
+                )
+                .exit()
+        }
+    }
+
+    let color = if args.no_color {
+        false
+    } else if args.output.is_some() {
+        // Escapes would only garble a file, so `auto` never colors one
+        args.color == ColorMode::Always
+    } else {
+        args.color.enabled(io::stdout().is_terminal())
+    };
 
     let mut output: Box<dyn Write> = match &args.output {
-        Some(file_path) => Box::new(File::create(file_path)?),
+        Some(file_path) => Box::new(BufWriter::new(File::create(file_path)?)),
         None => Box::new(io::stdout()),
     };
 
-    // Create a vector to track the last entry at each depth
-    let mut last_dirs: Vec<bool> = Vec::new();
-
-    // Configure WalkDir
-    let walker = WalkDir::new(&target_dir)
-        .follow_links(false)
-        .into_iter()
-        .filter_entry(|e| {
-            if e.depth() == 0 {
-                // Always include the root directory
-                true
-            } else {
-                // For other entries, include them if they're not hidden or if show_hidden is true
-                !is_hidden(e) || show_hidden
-            }
-        })
-        .filter_map(|e| e.ok());
-
-    // Collect entries into a vector
-    let entries: Vec<_> = if let Some(max_depth) = args.level {
-        walker
-            .filter(|e: &walkdir::DirEntry| e.depth() <= max_depth)
-            .collect()
+    let naming = if args.full_path {
+        Naming::Full
+    } else if args.absolute {
+        Naming::Absolute
+    } else if let Some(dir) = &args.relative_to {
+        Naming::RelativeTo(dir.clone())
     } else {
-        walker.collect()
+        Naming::FileName
+    };
+    let builder = |root: &str| {
+        TreeBuilder::new(root)
+            .max_depth(args.level)
+            .show_hidden(args.all)
+            .gitignore(!args.no_gitignore)
+            .require_git(!args.gitignore)
+            .patterns(patterns.clone())
+            .prune(args.prune)
+            .du(args.du)
+            .allocated(args.allocated)
+            .follow(args.follow)
+            .link_chains(args.link_chain)
+            .broken_links_only(args.broken_links_only)
+            .dirs_only(args.dirs_only)
+            .files_only(args.files_only)
+            .naming(naming.clone())
+            .file_limit(args.filelimit)
+            .max_children(args.max_children)
+            .sort(Sort {
+                by: args.sort,
+                reverse: args.reverse,
+                dir_order: if args.dirsfirst {
+                    DirOrder::DirsFirst
+                } else if args.filesfirst {
+                    DirOrder::FilesFirst
+                } else {
+                    DirOrder::Mixed
+                },
+            })
     };
 
-    for (index, entry) in entries.iter().enumerate() {
-        let depth = entry.depth();
-        let file_name = entry.file_name().to_string_lossy();
-
-        // Determine if this is the last entry at its depth
-        let is_last = {
-            let next_index = index + 1;
-            if next_index >= entries.len() {
-                true
-            } else {
-                let next_entry = &entries[next_index];
-                next_entry.depth() < depth
-            }
+    if args.format == Format::Jsonl {
+        // Stream entries as they are found instead of building the tree first
+        let renderer = JsonLinesRenderer {
+            no_report: args.noreport,
         };
-
-        // Update last_dirs vector
-        if depth >= last_dirs.len() {
-            last_dirs.push(is_last);
-        } else {
-            last_dirs[depth] = is_last;
-        }
-
-        // Build the prefix
-        let mut prefix = String::new();
-        // Choose the branch character
-        if depth > 0 {
-            for d in 1..depth {
-                if last_dirs.get(d).cloned().unwrap_or(false) {
-                    prefix.push_str("    ");
-                } else {
-                    prefix.push_str("│   ");
+        let mut report = Report::default();
+        let mut errors = Vec::new();
+        for root in &args.roots {
+            errors.extend(builder(root).walk(|node| {
+                if node.depth > 0 {
+                    report.add(&node);
                 }
-            }
-
-            if is_last {
-                prefix.push_str("└── ");
-            } else {
-                prefix.push_str("├── ");
-            }
+                renderer.write_entry(&mut output, &node)
+            })?);
         }
+        report.errors = errors.len();
+        renderer.write_report(&mut output, &report)?;
+        output.flush()?;
+        return Ok(errors);
+    }
 
-        // Determine if the entry is a symbolic link
-        let styled_name = if entry.file_type().is_dir() {
-            if args.no_color {
-                file_name.bold()
-            } else {
-                file_name.bold().blue()
-            }
-        } else if entry.file_type().is_symlink() {
-            if args.no_color {
-                file_name.normal().green()
-            } else {
-                file_name.normal().green()
-            }
-        } else {
-            file_name.normal()
-        };
+    let trees = args
+        .roots
+        .iter()
+        .map(|root| builder(root).build())
+        .collect::<io::Result<Vec<_>>>()?;
 
-        // Append symlink target if applicable
-        let display_name = if entry.file_type().is_symlink() {
-            if let Ok(target) = entry.path().read_link() {
-                format!("{} -> {}", styled_name, target.display())
-            } else {
-                format!("{} -> [unresolved]", styled_name)
-            }
+    let columns = Columns {
+        permissions: args.permissions,
+        octal: args.octal,
+        user: args.user,
+        group: args.group,
+        size: if args.no_size {
+            None
+        } else if args.human {
+            Some(SizeFormat::Iec)
+        } else if args.si {
+            Some(SizeFormat::Si)
         } else {
-            styled_name.to_string()
-        };
-
-        let metadata = entry.metadata().unwrap();
-        let file_size = metadata.len();
-        let modified: DateTime<Local> = metadata.modified().unwrap().into();
-        let formatted_date = modified.format("%Y-%m-%d %H:%M:%S").to_string();
-
-        // Print the entry
-        writeln!(
-            output,
-            "{}{} ({} bytes, modified: {})",
-            prefix, display_name, file_size, formatted_date
-        )?;
-    }
-
-    Ok(())
-}
+            Some(SizeFormat::Bytes)
+        },
+        time: (!args.no_time).then_some(TimeColumn {
+            field: args.time,
+            style: args.time_style,
+            strftime: args.timefmt,
+            utc: args.utc,
+        }),
+    };
 
-// Helper function to determine if a file is hidden
-fn is_hidden(entry: &walkdir::DirEntry) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s.starts_with('.'))
-        .unwrap_or(false)
+    let renderer: Box<dyn Render> = match args.format {
+        Format::Text => Box::new(TextRenderer {
+            no_color: !color,
+            colors: LsColors::from_env(),
+            link_chains: args.link_chain,
+            template: args.template,
+            compact_chains: args.compact_chains,
+            columns,
+            no_report: args.noreport,
+        }),
+        Format::Json => Box::new(JsonRenderer {
+            no_report: args.noreport,
+        }),
+        Format::Jsonl => Box::new(JsonLinesRenderer {
+            no_report: args.noreport,
+        }),
+        Format::Xml => Box::new(XmlRenderer {
+            columns,
+            no_report: args.noreport,
+        }),
+        Format::Html => Box::new(HtmlRenderer {
+            base_href: args.base_href,
+            compact_chains: args.compact_chains,
+            columns,
+            no_report: args.noreport,
+        }),
+    };
+    renderer.render_all(&trees, &mut output)?;
+    output.flush()?;
+    Ok(trees.into_iter().flat_map(|tree| tree.errors).collect())
 }
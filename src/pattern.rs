@@ -0,0 +1,95 @@
+//! Include and exclude glob filters, in the style of GNU tree's `-P` and `-I`.
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Globs matched against a name, or against the path below the root for
+/// globs containing a `/`.
+#[derive(Debug, Clone)]
+struct Matcher {
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl Matcher {
+    /// Compiles `patterns`, each of which may hold several globs separated by `|`.
+    fn new<S: AsRef<str>>(patterns: &[S], ignore_case: bool) -> Result<Self, globset::Error> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for glob in patterns.iter().flat_map(|p| p.as_ref().split('|')) {
+            let compiled = GlobBuilder::new(glob.trim_start_matches('/'))
+                .case_insensitive(ignore_case)
+                .literal_separator(true)
+                .build()?;
+            if glob.contains('/') {
+                paths.add(compiled);
+            } else {
+                names.add(compiled);
+            }
+        }
+        Ok(Matcher {
+            names: names.build()?,
+            paths: paths.build()?,
+        })
+    }
+
+    fn is_match(&self, name: &OsStr, relative: &Path) -> bool {
+        self.names.is_match(name) || self.paths.is_match(relative)
+    }
+}
+
+/// Filters entries by glob patterns on their names.
+#[derive(Debug, Clone, Default)]
+pub struct Patterns {
+    include: Option<Matcher>,
+    exclude: Option<Matcher>,
+    /// Also match directory names against the include patterns; everything
+    /// below a matching directory is then listed
+    pub match_dirs: bool,
+}
+
+impl Patterns {
+    /// Builds the filter from include and exclude patterns.
+    ///
+    /// A pattern may list alternatives separated by `|`, such as `*.rs|*.toml`.
+    /// Empty lists disable the corresponding filter.
+    pub fn new<S: AsRef<str>>(
+        include: &[S],
+        exclude: &[S],
+        ignore_case: bool,
+    ) -> Result<Self, globset::Error> {
+        let matcher = |patterns: &[S]| {
+            if patterns.is_empty() {
+                Ok(None)
+            } else {
+                Matcher::new(patterns, ignore_case).map(Some)
+            }
+        };
+        Ok(Patterns {
+            include: matcher(include)?,
+            exclude: matcher(exclude)?,
+            match_dirs: false,
+        })
+    }
+
+    pub fn has_include(&self) -> bool {
+        self.include.is_some()
+    }
+
+    /// True if the entry matches an include pattern, or there are none.
+    ///
+    /// `relative` is the path of the entry below the root.
+    pub fn is_included(&self, name: &OsStr, relative: &Path) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|m| m.is_match(name, relative))
+    }
+
+    /// True if the entry matches an exclude pattern.
+    pub fn is_excluded(&self, name: &OsStr, relative: &Path) -> bool {
+        self.exclude
+            .as_ref()
+            .is_some_and(|m| m.is_match(name, relative))
+    }
+}
@@ -0,0 +1,319 @@
+use super::owners::{group_name, user_name};
+use crate::tree::Node;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, SecondsFormat, TimeZone, Utc};
+use std::fmt::Display;
+use std::time::SystemTime;
+
+/// How sizes are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SizeFormat {
+    /// Exact number of bytes
+    #[default]
+    Bytes,
+    /// Powers of 1024 with the units K, M, G, ... like `tree -h`
+    Iec,
+    /// Powers of 1000 with the units k, M, G, ... like `tree --si`
+    Si,
+}
+
+impl SizeFormat {
+    /// Width the text output right-aligns sizes to, as GNU tree does
+    pub fn width(self) -> usize {
+        match self {
+            SizeFormat::Bytes => 11,
+            SizeFormat::Iec | SizeFormat::Si => 4,
+        }
+    }
+
+    /// Like [`SizeFormat::format`] with the unit spelled out, as in `5.6 GiB`;
+    /// exact bytes are shown in powers of 1024 too
+    pub fn format_long(self, size: u64) -> String {
+        let (format, suffix) = match self {
+            SizeFormat::Si => (SizeFormat::Si, "B"),
+            SizeFormat::Bytes | SizeFormat::Iec => (SizeFormat::Iec, "iB"),
+        };
+        let mut short = format.format(size);
+        match short.pop() {
+            Some(unit) if unit.is_ascii_alphabetic() => format!("{} {}{}", short, unit, suffix),
+            _ => format!("{} B", size),
+        }
+    }
+
+    pub fn format(self, size: u64) -> String {
+        let (base, units) = match self {
+            SizeFormat::Bytes => return size.to_string(),
+            SizeFormat::Iec => (1024.0, ['K', 'M', 'G', 'T', 'P', 'E']),
+            SizeFormat::Si => (1000.0, ['k', 'M', 'G', 'T', 'P', 'E']),
+        };
+
+        let mut value = size as f64;
+        if value < base {
+            return size.to_string();
+        }
+        let mut unit = 0;
+        value /= base;
+        while value >= base && unit + 1 < units.len() {
+            value /= base;
+            unit += 1;
+        }
+        // One decimal for small values only, like `ls -h`
+        if value < 9.95 {
+            format!("{:.1}{}", value, units[unit])
+        } else {
+            format!("{:.0}{}", value, units[unit])
+        }
+    }
+}
+
+/// Which of the timestamps of an entry is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum TimeField {
+    /// Last modification
+    #[default]
+    Mtime,
+    /// Last access
+    Atime,
+    /// Last status change
+    Ctime,
+    /// Creation, where the filesystem records it
+    Birth,
+}
+
+impl TimeField {
+    /// The timestamp of `node`, if known
+    pub fn of(self, node: &Node) -> Option<SystemTime> {
+        let metadata = node.metadata.as_ref()?;
+        match self {
+            TimeField::Mtime => metadata.modified,
+            TimeField::Atime => metadata.accessed,
+            TimeField::Ctime => metadata.changed,
+            TimeField::Birth => metadata.created,
+        }
+    }
+}
+
+/// Preset ways of writing timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum TimeStyle {
+    /// 2024-05-01 13:37:00
+    #[default]
+    Default,
+    /// 2024-05-01T13:37:00, ISO 8601 without the offset
+    Iso,
+    /// 2024-05-01T13:37:00+02:00
+    Rfc3339,
+    /// 3 days ago
+    Relative,
+}
+
+/// The timestamp shown next to each name.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TimeColumn {
+    pub field: TimeField,
+    pub style: TimeStyle,
+    /// A strftime format overriding `style`, see [`TimeColumn::check_strftime`]
+    pub strftime: Option<String>,
+    /// Write times in UTC rather than the local time zone
+    pub utc: bool,
+}
+
+impl TimeColumn {
+    /// Fails with a message if `format` holds specifiers chrono does not know,
+    /// which it would otherwise only report by panicking while writing.
+    pub fn check_strftime(format: &str) -> Result<(), String> {
+        match StrftimeItems::new(format).position(|item| item == Item::Error) {
+            Some(_) => Err("not a valid strftime format".to_string()),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the timestamp of `node`, `?` if unknown.
+    pub fn format(&self, node: &Node) -> String {
+        let Some(time) = self.field.of(node) else {
+            return "?".to_string();
+        };
+        if self.style == TimeStyle::Relative && self.strftime.is_none() {
+            return relative(time, SystemTime::now());
+        }
+        if self.utc {
+            self.format_in(DateTime::<Utc>::from(time))
+        } else {
+            self.format_in(DateTime::<Local>::from(time))
+        }
+    }
+
+    fn format_in<Tz: TimeZone>(&self, time: DateTime<Tz>) -> String
+    where
+        Tz::Offset: Display,
+    {
+        let format = match (&self.strftime, self.style) {
+            (Some(format), _) => format.as_str(),
+            (None, TimeStyle::Rfc3339) => {
+                return time.to_rfc3339_opts(SecondsFormat::Secs, self.utc);
+            }
+            (None, TimeStyle::Iso) => "%Y-%m-%dT%H:%M:%S",
+            (None, _) => "%Y-%m-%d %H:%M:%S",
+        };
+        time.format(format).to_string()
+    }
+}
+
+/// How long before or after `now` the `time` is, in its largest whole unit.
+fn relative(time: SystemTime, now: SystemTime) -> String {
+    let (seconds, future) = match now.duration_since(time) {
+        Ok(elapsed) => (elapsed.as_secs(), false),
+        Err(err) => (err.duration().as_secs(), true),
+    };
+    const UNITS: [(u64, &str); 6] = [
+        (365 * 24 * 3600, "year"),
+        (30 * 24 * 3600, "month"),
+        (7 * 24 * 3600, "week"),
+        (24 * 3600, "day"),
+        (3600, "hour"),
+        (60, "minute"),
+    ];
+    let Some(&(length, unit)) = UNITS.iter().find(|(length, _)| seconds >= *length) else {
+        return "just now".to_string();
+    };
+    let count = seconds / length;
+    let unit = if count == 1 {
+        unit.to_string()
+    } else {
+        format!("{}s", unit)
+    };
+    if future {
+        format!("in {} {}", count, unit)
+    } else {
+        format!("{} {} ago", count, unit)
+    }
+}
+
+/// Metadata shown next to each name by the text, HTML and XML renderers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Columns {
+    /// Permissions like `drwxr-xr-x`
+    pub permissions: bool,
+    /// Permissions like `0755`
+    pub octal: bool,
+    /// Name of the owning user
+    pub user: bool,
+    /// Name of the owning group
+    pub group: bool,
+    /// How sizes are shown, `None` to hide them
+    pub size: Option<SizeFormat>,
+    /// `None` to hide the timestamps
+    pub time: Option<TimeColumn>,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Columns {
+            permissions: false,
+            octal: false,
+            user: false,
+            group: false,
+            size: Some(SizeFormat::Bytes),
+            time: Some(TimeColumn::default()),
+        }
+    }
+}
+
+impl Columns {
+    /// The enabled permission, user and group fields of `node` with their
+    /// names, `?` where unknown
+    pub(crate) fn owner_fields(&self, node: &Node) -> Vec<(&'static str, String)> {
+        let metadata = node.metadata.as_ref();
+        let mode = metadata.and_then(|m| m.mode);
+        let show = |value: Option<String>| value.unwrap_or_else(|| "?".to_string());
+
+        let mut fields = Vec::new();
+        if self.permissions {
+            fields.push(("prot", show(mode.map(symbolic_mode))));
+        }
+        if self.octal {
+            fields.push(("mode", show(mode.map(octal_mode))));
+        }
+        if self.user {
+            fields.push(("user", show(metadata.and_then(|m| m.uid).map(user_name))));
+        }
+        if self.group {
+            fields.push(("group", show(metadata.and_then(|m| m.gid).map(group_name))));
+        }
+        fields
+    }
+}
+
+/// Formats a Unix mode like `ls -l`, e.g. `drwxr-xr-x` or `-rwsr-x--T`.
+pub(crate) fn symbolic_mode(mode: u32) -> String {
+    let kind = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o010000 => 'p',
+        0o140000 => 's',
+        0o060000 => 'b',
+        0o020000 => 'c',
+        _ => '-',
+    };
+    let mut symbolic = String::with_capacity(10);
+    symbolic.push(kind);
+    // Read, write and execute for the user, group and others, with the
+    // setuid, setgid and sticky bits shown in place of execute
+    for (shift, special, set, unset) in [
+        (6, 0o4000, 's', 'S'),
+        (3, 0o2000, 's', 'S'),
+        (0, 0o1000, 't', 'T'),
+    ] {
+        let bits = (mode >> shift) & 0o7;
+        symbolic.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        symbolic.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        symbolic.push(match (mode & special != 0, bits & 0o1 != 0) {
+            (true, true) => set,
+            (true, false) => unset,
+            (false, true) => 'x',
+            (false, false) => '-',
+        });
+    }
+    symbolic
+}
+
+/// Formats the permission bits of a Unix mode in octal, e.g. `0755`.
+pub(crate) fn octal_mode(mode: u32) -> String {
+    format!("{:04o}", mode & 0o7777)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbolic_mode_by_type_and_permissions() {
+        assert_eq!(symbolic_mode(0o100644), "-rw-r--r--");
+        assert_eq!(symbolic_mode(0o040755), "drwxr-xr-x");
+        assert_eq!(symbolic_mode(0o120777), "lrwxrwxrwx");
+        assert_eq!(symbolic_mode(0o010600), "prw-------");
+        assert_eq!(symbolic_mode(0o140755), "srwxr-xr-x");
+        assert_eq!(symbolic_mode(0o060660), "brw-rw----");
+        assert_eq!(symbolic_mode(0o020620), "crw--w----");
+        assert_eq!(symbolic_mode(0o100000), "----------");
+    }
+
+    #[test]
+    fn symbolic_mode_special_bits() {
+        assert_eq!(symbolic_mode(0o104755), "-rwsr-xr-x");
+        assert_eq!(symbolic_mode(0o104644), "-rwSr--r--");
+        assert_eq!(symbolic_mode(0o102755), "-rwxr-sr-x");
+        assert_eq!(symbolic_mode(0o102745), "-rwxr-Sr-x");
+        assert_eq!(symbolic_mode(0o041777), "drwxrwxrwt");
+        assert_eq!(symbolic_mode(0o041776), "drwxrwxrwT");
+        assert_eq!(symbolic_mode(0o107777), "-rwsrwsrwt");
+        assert_eq!(symbolic_mode(0o107000), "---S--S--T");
+    }
+
+    #[test]
+    fn octal_mode_keeps_special_bits() {
+        assert_eq!(octal_mode(0o100644), "0644");
+        assert_eq!(octal_mode(0o041777), "1777");
+        assert_eq!(octal_mode(0o106755), "6755");
+    }
+}
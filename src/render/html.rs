@@ -0,0 +1,240 @@
+use super::{compact_chain, omitted_line, summary, xml_escape, Columns, Render};
+use crate::tree::{Node, Tree};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const STYLE: &str = "\
+body { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; font-size: 14px; margin: 2em; color: #24292f; }
+h1 { font-size: 1.2em; }
+ul { list-style: none; margin: 0; padding-left: 1.5em; border-left: 1px solid #d0d7de; }
+summary { cursor: pointer; display: flex; list-style: none; }
+summary::-webkit-details-marker { display: none; }
+summary::before { content: \"\\25b8\"; flex: none; width: 1.2em; }
+details[open] > summary::before { content: \"\\25be\"; }
+summary > .entry { flex: 1; }
+li > .entry { margin-left: 1.2em; }
+.entry { display: flex; gap: 2em; padding: 1px 0; }
+.entry:hover { background: #f6f8fa; }
+.name { flex: 1; }
+.dir { font-weight: bold; color: #0550ae; }
+.link { color: #116329; }
+.broken { color: #cf222e; }
+.error { color: #cf222e; font-weight: normal; }
+.prot, .mode, .user, .group { color: #57606a; }
+.size { min-width: 8em; text-align: right; color: #57606a; }
+.time { min-width: 11em; color: #57606a; }
+a { color: inherit; text-decoration: none; }
+a:hover { text-decoration: underline; }
+.more { margin-left: 1.2em; color: #57606a; font-style: italic; }
+.report { margin-top: 1em; color: #57606a; }
+";
+
+/// Renders a self-contained HTML page, with directories as collapsible
+/// `<details>` elements and links to every file.
+#[derive(Debug, Clone, Default)]
+pub struct HtmlRenderer {
+    /// Prefix for the links, which are otherwise relative to the root, or to
+    /// the common parent of the roots when there are several
+    pub base_href: Option<String>,
+    pub columns: Columns,
+    /// Show directories holding only another directory as one, like `a/b/c/`
+    pub compact_chains: bool,
+    /// Leave out the closing line with the counts
+    pub no_report: bool,
+}
+
+impl Render for HtmlRenderer {
+    fn render_all(&self, trees: &[Tree], out: &mut dyn Write) -> io::Result<()> {
+        let names: Vec<_> = trees.iter().map(|tree| tree.root.name.as_str()).collect();
+        let title = xml_escape(&names.join(" "));
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html lang=\"en\">")?;
+        writeln!(out, "<head>")?;
+        writeln!(out, "<meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>{}</title>", title)?;
+        writeln!(out, "<style>\n{}</style>", STYLE)?;
+        writeln!(out, "</head>")?;
+        writeln!(out, "<body>")?;
+        writeln!(out, "<h1>{}</h1>", title)?;
+
+        for (tree, prefix) in trees.iter().zip(root_prefixes(trees)) {
+            let base = LinkBase {
+                root: &tree.root.path,
+                prefix,
+            };
+            self.write_node(out, &tree.root, &base)?;
+        }
+
+        if !self.no_report {
+            let report = trees.iter().map(|tree| tree.report).sum();
+            writeln!(
+                out,
+                "<p class=\"report\">{}</p>",
+                summary(&report, self.columns.size)
+            )?;
+        }
+        writeln!(out, "</body>")?;
+        writeln!(out, "</html>")
+    }
+}
+
+impl HtmlRenderer {
+    fn write_node(&self, out: &mut dyn Write, node: &Node, base: &LinkBase) -> io::Result<()> {
+        if !node.is_dir() {
+            return self.write_entry(out, node, base);
+        }
+
+        let chain = self.compact_chains.then(|| compact_chain(node)).flatten();
+        let (shown, node) = match &chain {
+            Some((stand_in, last)) => (stand_in, *last),
+            None => (node, node),
+        };
+        writeln!(out, "<details open>")?;
+        write!(out, "<summary>")?;
+        self.write_entry(out, shown, base)?;
+        writeln!(out, "</summary>")?;
+        if !node.children.is_empty() || node.omitted.is_some() {
+            writeln!(out, "<ul>")?;
+            for child in &node.children {
+                write!(out, "<li>")?;
+                self.write_node(out, child, base)?;
+                writeln!(out, "</li>")?;
+            }
+            if let Some(omitted) = &node.omitted {
+                writeln!(
+                    out,
+                    "<li class=\"more\">{}</li>",
+                    omitted_line(omitted, self.columns.size)
+                )?;
+            }
+            writeln!(out, "</ul>")?;
+        }
+        write!(out, "</details>")
+    }
+
+    /// Writes the row for a single entry: its name and metadata columns.
+    fn write_entry(&self, out: &mut dyn Write, node: &Node, base: &LinkBase) -> io::Result<()> {
+        let name = xml_escape(&node.name);
+        write!(out, "<span class=\"entry\">")?;
+        if node.is_dir() {
+            // Compacted chains already end with a slash
+            let name = name.strip_suffix('/').unwrap_or(&name);
+            write!(out, "<span class=\"name dir\">{}/", name)?;
+        } else {
+            let class = if node.broken {
+                "name link broken"
+            } else if node.is_symlink() {
+                "name link"
+            } else {
+                "name"
+            };
+            write!(
+                out,
+                "<span class=\"{}\"><a href=\"{}\">{}</a>",
+                class,
+                xml_escape(&self.href(node, base)),
+                name
+            )?;
+            if let Some(target) = &node.link_target {
+                write!(out, " -&gt; {}", xml_escape(&target.to_string_lossy()))?;
+            }
+            if node.broken {
+                write!(out, " [broken]")?;
+            }
+        }
+        if let Some(error) = &node.error {
+            write!(out, " <span class=\"error\">[{}]</span>", xml_escape(error))?;
+        }
+        write!(out, "</span>")?;
+
+        for (column, value) in self.columns.owner_fields(node) {
+            write!(
+                out,
+                "<span class=\"{}\">{}</span>",
+                column,
+                xml_escape(&value)
+            )?;
+        }
+        if let Some(size) = self.columns.size {
+            write!(
+                out,
+                "<span class=\"size\">{}</span>",
+                node.size()
+                    .map_or_else(|| "?".to_string(), |bytes| size.format(bytes))
+            )?;
+        }
+        if let Some(time) = &self.columns.time {
+            write!(
+                out,
+                "<span class=\"time\">{}</span>",
+                xml_escape(&time.format(node))
+            )?;
+        }
+        write!(out, "</span>")
+    }
+
+    fn href(&self, node: &Node, base: &LinkBase) -> String {
+        let relative = node.path.strip_prefix(base.root).unwrap_or(&node.path);
+        let encoded = encode_path(&base.prefix.join(relative));
+        match &self.base_href {
+            Some(base) if base.ends_with('/') => format!("{}{}", base, encoded),
+            Some(base) => format!("{}/{}", base, encoded),
+            None => encoded,
+        }
+    }
+}
+
+/// Where the links of one tree start from.
+struct LinkBase<'a> {
+    /// Root of the tree, which the paths of its nodes start with
+    root: &'a Path,
+    /// The way to the root from the common parent of all roots
+    prefix: PathBuf,
+}
+
+/// The way from the common parent of the roots of `trees` to each of them,
+/// so their links do not collide; empty for a single root.
+fn root_prefixes(trees: &[Tree]) -> Vec<PathBuf> {
+    if trees.len() < 2 {
+        return vec![PathBuf::new(); trees.len()];
+    }
+    let roots: Vec<PathBuf> = trees
+        .iter()
+        .map(|tree| {
+            let path = &tree.root.path;
+            fs::canonicalize(path)
+                .or_else(|_| std::path::absolute(path))
+                .unwrap_or_else(|_| path.clone())
+        })
+        .collect();
+    let mut common = roots[0].as_path();
+    for root in &roots[1..] {
+        common = common
+            .ancestors()
+            .find(|dir| root.starts_with(dir))
+            .unwrap_or(Path::new(""));
+    }
+    roots
+        .iter()
+        .map(|root| root.strip_prefix(common).unwrap_or(root).to_path_buf())
+        .collect()
+}
+
+/// Percent-encodes a relative path for use in a URL, keeping the separators.
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for (index, component) in path.iter().enumerate() {
+        if index > 0 {
+            encoded.push('/');
+        }
+        for &byte in component.as_encoded_bytes() {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,95 @@
+use super::{json_report, json_string, type_name, Render};
+use crate::tree::{Node, Tree};
+use chrono::{DateTime, Local, SecondsFormat};
+use std::io::{self, Write};
+
+/// Renders a nested JSON document shaped like the output of GNU `tree -J`.
+///
+/// The document is an array holding each root directory, whose children are
+/// listed under `contents`, followed by a `report` object with the counts.
+#[derive(Debug, Clone, Default)]
+pub struct JsonRenderer {
+    /// Leave out the `report` object
+    pub no_report: bool,
+}
+
+impl Render for JsonRenderer {
+    fn render_all(&self, trees: &[Tree], out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "[")?;
+        for (index, tree) in trees.iter().enumerate() {
+            if index > 0 {
+                writeln!(out, ",")?;
+            }
+            write_node(out, &tree.root, 1)?;
+        }
+        if !self.no_report {
+            let report = trees.iter().map(|tree| tree.report).sum();
+            writeln!(out, "\n,")?;
+            write!(out, "  {{{}}}", json_report(&report))?;
+        }
+        writeln!(out, "\n]")
+    }
+}
+
+/// Writes the fields describing `node`, without the surrounding braces.
+fn write_fields(out: &mut dyn Write, node: &Node) -> io::Result<()> {
+    write!(
+        out,
+        "\"type\":\"{}\",\"name\":{},\"path\":{}",
+        type_name(node.kind),
+        json_string(&node.name),
+        json_string(&node.path.to_string_lossy())
+    )?;
+    if let Some(target) = &node.link_target {
+        write!(
+            out,
+            ",\"target\":{}",
+            json_string(&target.to_string_lossy())
+        )?;
+    }
+    if node.broken {
+        write!(out, ",\"broken\":true")?;
+    }
+
+    if let Some(size) = node.size() {
+        write!(out, ",\"size\":{}", size)?;
+    }
+    if let Some(modified) = node.modified() {
+        let modified: DateTime<Local> = modified.into();
+        write!(
+            out,
+            ",\"time\":\"{}\"",
+            modified.to_rfc3339_opts(SecondsFormat::Secs, false)
+        )?;
+    }
+    if let Some(error) = &node.error {
+        write!(out, ",\"error\":{}", json_string(error))?;
+    }
+    if let Some(omitted) = &node.omitted {
+        write!(
+            out,
+            ",\"omitted\":{{\"entries\":{},\"bytes\":{}}}",
+            omitted.entries, omitted.bytes
+        )?;
+    }
+    Ok(())
+}
+
+fn write_node(out: &mut dyn Write, node: &Node, level: usize) -> io::Result<()> {
+    let indent = "  ".repeat(level);
+    write!(out, "{}{{", indent)?;
+    write_fields(out, node)?;
+
+    if node.is_dir() {
+        write!(out, ",\"contents\":[")?;
+        for (index, child) in node.children.iter().enumerate() {
+            write!(out, "{}", if index == 0 { "\n" } else { ",\n" })?;
+            write_node(out, child, level + 1)?;
+        }
+        if !node.children.is_empty() {
+            write!(out, "\n{}", indent)?;
+        }
+        write!(out, "]")?;
+    }
+    write!(out, "}}")
+}
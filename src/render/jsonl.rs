@@ -0,0 +1,77 @@
+use super::{json_report, json_string, type_name, Render};
+use crate::tree::{Node, Report, Tree};
+use chrono::{DateTime, Local, SecondsFormat};
+use std::io::{self, Write};
+
+/// Renders one flat JSON object per line for every entry.
+///
+/// Lines only depend on their own entry, so [`JsonLinesRenderer::write_entry`]
+/// can be fed straight from [`crate::TreeBuilder::walk`] to stream huge trees
+/// in constant memory. The counts follow on a last line of type `report`.
+#[derive(Debug, Clone, Default)]
+pub struct JsonLinesRenderer {
+    /// Leave out the `report` line
+    pub no_report: bool,
+}
+
+impl Render for JsonLinesRenderer {
+    fn render_all(&self, trees: &[Tree], out: &mut dyn Write) -> io::Result<()> {
+        for node in trees.iter().flat_map(Tree::iter) {
+            self.write_entry(out, node)?;
+        }
+        self.write_report(out, &trees.iter().map(|tree| tree.report).sum())
+    }
+}
+
+impl JsonLinesRenderer {
+    /// Writes the closing line with the counts, unless disabled.
+    pub fn write_report(&self, out: &mut dyn Write, report: &Report) -> io::Result<()> {
+        if self.no_report {
+            return Ok(());
+        }
+        writeln!(out, "{{{}}}", json_report(report))
+    }
+
+    /// Writes the line for a single entry.
+    pub fn write_entry(&self, out: &mut dyn Write, node: &Node) -> io::Result<()> {
+        let parent = match node.path.parent() {
+            Some(parent) if node.depth > 0 => json_string(&parent.to_string_lossy()),
+            _ => "null".to_string(),
+        };
+        write!(
+            out,
+            "{{\"path\":{},\"name\":{},\"depth\":{},\"parent\":{},\"type\":\"{}\"",
+            json_string(&node.path.to_string_lossy()),
+            json_string(&node.name),
+            node.depth,
+            parent,
+            type_name(node.kind)
+        )?;
+        if let Some(target) = &node.link_target {
+            write!(
+                out,
+                ",\"target\":{}",
+                json_string(&target.to_string_lossy())
+            )?;
+        }
+        if node.broken {
+            write!(out, ",\"broken\":true")?;
+        }
+
+        if let Some(size) = node.size() {
+            write!(out, ",\"size\":{}", size)?;
+        }
+        if let Some(modified) = node.modified() {
+            let modified: DateTime<Local> = modified.into();
+            write!(
+                out,
+                ",\"mtime\":\"{}\"",
+                modified.to_rfc3339_opts(SecondsFormat::Secs, false)
+            )?;
+        }
+        if let Some(error) = &node.error {
+            write!(out, ",\"error\":{}", json_string(error))?;
+        }
+        writeln!(out, "}}")
+    }
+}
@@ -0,0 +1,324 @@
+use crate::builder::node_kind;
+use crate::tree::{Node, NodeKind};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// The database printed by `dircolors`, used when `LS_COLORS` is unset.
+const DEFAULT: &str = "\
+rs=0:di=01;34:ln=01;36:mh=00:pi=40;33:so=01;35:do=01;35:bd=40;33;01:cd=40;33;01:\
+or=40;31;01:mi=00:su=37;41:sg=30;43:ca=00:tw=30;42:ow=34;42:st=37;44:ex=01;32:\
+*.7z=01;31:*.ace=01;31:*.alz=01;31:*.apk=01;31:*.arc=01;31:*.arj=01;31:*.bz=01;31:\
+*.bz2=01;31:*.cab=01;31:*.cpio=01;31:*.crate=01;31:*.deb=01;31:*.drpm=01;31:\
+*.dwm=01;31:*.dz=01;31:*.ear=01;31:*.egg=01;31:*.esd=01;31:*.gz=01;31:*.jar=01;31:\
+*.lha=01;31:*.lrz=01;31:*.lz=01;31:*.lz4=01;31:*.lzh=01;31:*.lzma=01;31:*.lzo=01;31:\
+*.pyz=01;31:*.rar=01;31:*.rpm=01;31:*.rz=01;31:*.sar=01;31:*.swm=01;31:*.t7z=01;31:\
+*.tar=01;31:*.taz=01;31:*.tbz=01;31:*.tbz2=01;31:*.tgz=01;31:*.tlz=01;31:*.txz=01;31:\
+*.tz=01;31:*.tzo=01;31:*.tzst=01;31:*.udeb=01;31:*.war=01;31:*.whl=01;31:*.wim=01;31:\
+*.xz=01;31:*.z=01;31:*.zip=01;31:*.zoo=01;31:*.zst=01;31:\
+*.avif=01;35:*.jpg=01;35:*.jpeg=01;35:*.jxl=01;35:*.mjpg=01;35:*.mjpeg=01;35:\
+*.gif=01;35:*.bmp=01;35:*.pbm=01;35:*.pgm=01;35:*.ppm=01;35:*.tga=01;35:*.xbm=01;35:\
+*.xpm=01;35:*.tif=01;35:*.tiff=01;35:*.png=01;35:*.svg=01;35:*.svgz=01;35:*.mng=01;35:\
+*.pcx=01;35:*.mov=01;35:*.mpg=01;35:*.mpeg=01;35:*.m2v=01;35:*.mkv=01;35:*.webm=01;35:\
+*.webp=01;35:*.ogm=01;35:*.mp4=01;35:*.m4v=01;35:*.mp4v=01;35:*.vob=01;35:*.qt=01;35:\
+*.nuv=01;35:*.wmv=01;35:*.asf=01;35:*.rm=01;35:*.rmvb=01;35:*.flc=01;35:*.avi=01;35:\
+*.fli=01;35:*.flv=01;35:*.gl=01;35:*.dl=01;35:*.xcf=01;35:*.xwd=01;35:*.yuv=01;35:\
+*.cgm=01;35:*.emf=01;35:*.ogv=01;35:*.ogx=01;35:\
+*.aac=00;36:*.au=00;36:*.flac=00;36:*.m4a=00;36:*.mid=00;36:*.midi=00;36:*.mka=00;36:\
+*.mp3=00;36:*.mpc=00;36:*.ogg=00;36:*.ra=00;36:*.wav=00;36:*.oga=00;36:*.opus=00;36:\
+*.spx=00;36:*.xspf=00;36:\
+*~=00;90:*#=00;90:*.bak=00;90:*.crdownload=00;90:*.dpkg-dist=00;90:*.dpkg-new=00;90:\
+*.dpkg-old=00;90:*.dpkg-tmp=00;90:*.old=00;90:*.orig=00;90:*.part=00;90:*.rej=00;90:\
+*.rpmnew=00;90:*.rpmorig=00;90:*.rpmsave=00;90:*.swp=00;90:*.tmp=00;90:*.ucf-dist=00;90:\
+*.ucf-new=00;90:*.ucf-old=00;90";
+
+const SET_UID: u32 = 0o4000;
+const SET_GID: u32 = 0o2000;
+const STICKY: u32 = 0o1000;
+const OTHER_WRITABLE: u32 = 0o002;
+const EXECUTABLE: u32 = 0o111;
+
+/// Colors for file names, configured through `LS_COLORS` as for `ls --color`.
+#[derive(Debug, Clone)]
+pub struct LsColors {
+    /// SGR parameters by file type key, e.g. `di` or `ex`
+    types: HashMap<String, String>,
+    /// SGR parameters by name suffix, in the order they were given
+    suffixes: Vec<(String, String)>,
+}
+
+impl Default for LsColors {
+    /// The `dircolors` defaults.
+    fn default() -> Self {
+        LsColors::parse(DEFAULT)
+    }
+}
+
+impl LsColors {
+    /// Reads `LS_COLORS`, falling back to the `dircolors` defaults when it is unset.
+    pub fn from_env() -> Self {
+        match env::var("LS_COLORS") {
+            Ok(value) if !value.is_empty() => LsColors::parse(&value),
+            _ => LsColors::default(),
+        }
+    }
+
+    /// Parses a `LS_COLORS` value such as `di=01;34:*.tar=01;31`, skipping
+    /// malformed entries.
+    pub fn parse(value: &str) -> Self {
+        let mut colors = LsColors {
+            types: HashMap::new(),
+            suffixes: Vec::new(),
+        };
+        for entry in value.split(':') {
+            let Some((key, sgr)) = entry.split_once('=') else {
+                continue;
+            };
+            match key.strip_prefix('*') {
+                Some(suffix) => colors.suffixes.push((suffix.to_string(), sgr.to_string())),
+                None => {
+                    colors.types.insert(key.to_string(), sgr.to_string());
+                }
+            }
+        }
+        colors
+    }
+
+    /// Wraps the name of `node` in its color, if any.
+    pub fn paint_name(&self, node: &Node) -> String {
+        paint(self.style(node), &node.name)
+    }
+
+    /// Wraps the target of the symlink `node` in the color of what it points to.
+    pub fn paint_target(&self, node: &Node, target: &Path) -> String {
+        let text = target.display().to_string();
+        let style = match fs::metadata(&node.path) {
+            Ok(metadata) => self.classify(
+                node_kind(metadata.file_type()),
+                mode(&metadata),
+                links(&metadata),
+                &target.to_string_lossy(),
+            ),
+            Err(_) => self.get("mi"),
+        };
+        paint(style, &text)
+    }
+
+    /// SGR parameters `ls` would use for the name of `node`
+    fn style(&self, node: &Node) -> Option<&str> {
+        let Some(metadata) = &node.metadata else {
+            return self.get("mi");
+        };
+        if node.broken {
+            return self.get("or").or_else(|| self.get("ln"));
+        }
+        if node.is_symlink() && self.types.get("ln").is_some_and(|sgr| sgr == "target") {
+            if let Ok(target) = fs::metadata(&node.path) {
+                return self.classify(
+                    node_kind(target.file_type()),
+                    mode(&target),
+                    links(&target),
+                    &node.name,
+                );
+            }
+        }
+        self.classify(
+            node.kind,
+            metadata.mode.unwrap_or(0),
+            metadata.links,
+            &node.name,
+        )
+    }
+
+    /// Picks the color for an entry of the given type, as `ls` does.
+    fn classify(&self, kind: NodeKind, mode: u32, links: u64, name: &str) -> Option<&str> {
+        let key = match kind {
+            NodeKind::Directory => {
+                let sticky = mode & STICKY != 0;
+                let writable = mode & OTHER_WRITABLE != 0;
+                if sticky && writable && self.get("tw").is_some() {
+                    "tw"
+                } else if writable && self.get("ow").is_some() {
+                    "ow"
+                } else if sticky && self.get("st").is_some() {
+                    "st"
+                } else {
+                    "di"
+                }
+            }
+            NodeKind::File => {
+                if mode & SET_UID != 0 && self.get("su").is_some() {
+                    "su"
+                } else if mode & SET_GID != 0 && self.get("sg").is_some() {
+                    "sg"
+                } else if mode & EXECUTABLE != 0 && self.get("ex").is_some() {
+                    "ex"
+                } else if links > 1 && self.get("mh").is_some() {
+                    "mh"
+                } else {
+                    // Suffixes only apply to files not colored by their mode
+                    return self.suffix(name).or_else(|| self.get("fi"));
+                }
+            }
+            NodeKind::Symlink => "ln",
+            NodeKind::Fifo => "pi",
+            NodeKind::Socket => "so",
+            NodeKind::BlockDevice => "bd",
+            NodeKind::CharDevice => "cd",
+            NodeKind::Other => "no",
+        };
+        self.get(key)
+    }
+
+    /// The color of the last suffix matching `name`, preferring exact case
+    fn suffix(&self, name: &str) -> Option<&str> {
+        let exact = self
+            .suffixes
+            .iter()
+            .rev()
+            .find(|(suffix, _)| name.ends_with(suffix.as_str()));
+        let found = exact.or_else(|| {
+            let name = name.to_ascii_lowercase();
+            self.suffixes
+                .iter()
+                .rev()
+                .find(|(suffix, _)| name.ends_with(&suffix.to_ascii_lowercase()))
+        });
+        found
+            .map(|(_, sgr)| sgr.as_str())
+            .filter(|sgr| is_colored(sgr))
+    }
+
+    /// The color set for a type key, `None` if unset or plain
+    fn get(&self, key: &str) -> Option<&str> {
+        self.types
+            .get(key)
+            .map(String::as_str)
+            // `ln=target` is resolved by `style`, it is no color itself
+            .filter(|sgr| is_colored(sgr) && *sgr != "target")
+    }
+}
+
+/// Whether `sgr` changes anything, `ls` treating `0` and `00` as no color.
+fn is_colored(sgr: &str) -> bool {
+    !matches!(sgr, "" | "0" | "00")
+}
+
+fn paint(style: Option<&str>, text: &str) -> String {
+    match style {
+        Some(sgr) => format!("\x1b[{}m{}\x1b[0m", sgr, text),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(unix)]
+fn mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.mode()
+}
+
+#[cfg(not(unix))]
+fn mode(_metadata: &fs::Metadata) -> u32 {
+    0
+}
+
+#[cfg(unix)]
+fn links(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+#[cfg(not(unix))]
+fn links(_metadata: &fs::Metadata) -> u64 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_splits_types_and_suffixes() {
+        let colors = LsColors::parse("di=01;34:*.tar=01;31::bogus:ln=target:*README=33");
+        assert_eq!(colors.types.get("di").map(String::as_str), Some("01;34"));
+        assert_eq!(colors.types.get("ln").map(String::as_str), Some("target"));
+        assert_eq!(
+            colors.suffixes,
+            [
+                (".tar".to_string(), "01;31".to_string()),
+                ("README".to_string(), "33".to_string()),
+            ]
+        );
+        assert!(!colors.types.contains_key("bogus"));
+        // `target` is resolved against the link, it is no color itself
+        assert_eq!(colors.get("ln"), None);
+    }
+
+    #[test]
+    fn plain_entries_are_no_color() {
+        let colors = LsColors::parse("di=0:fi=00:ex=:*.txt=00");
+        assert_eq!(colors.get("di"), None);
+        assert_eq!(colors.get("fi"), None);
+        assert_eq!(colors.get("ex"), None);
+        assert_eq!(colors.classify(NodeKind::File, 0o644, 1, "a.txt"), None);
+    }
+
+    #[test]
+    fn directories_by_sticky_and_writable_bits() {
+        let colors = LsColors::default();
+        let dir = |mode| colors.classify(NodeKind::Directory, mode, 2, "d");
+        assert_eq!(dir(0o755), Some("01;34"));
+        assert_eq!(dir(0o1777), Some("30;42"));
+        assert_eq!(dir(0o777), Some("34;42"));
+        assert_eq!(dir(0o1755), Some("37;44"));
+
+        // Without `tw`, a sticky writable directory falls back to `ow`
+        let colors = LsColors::parse("di=1:ow=2:st=3");
+        assert_eq!(
+            colors.classify(NodeKind::Directory, 0o1777, 2, "d"),
+            Some("2")
+        );
+    }
+
+    #[test]
+    fn files_by_mode_before_suffix() {
+        let colors = LsColors::parse("su=1:sg=2:ex=3:mh=4:fi=5:*.tar=6");
+        let file = |mode, links, name| colors.classify(NodeKind::File, mode, links, name);
+        assert_eq!(file(0o6755, 1, "a.tar"), Some("1"));
+        assert_eq!(file(0o2755, 1, "a.tar"), Some("2"));
+        assert_eq!(file(0o755, 2, "a.tar"), Some("3"));
+        assert_eq!(file(0o644, 2, "a.tar"), Some("4"));
+        assert_eq!(file(0o644, 1, "a.tar"), Some("6"));
+        assert_eq!(file(0o644, 1, "a.txt"), Some("5"));
+    }
+
+    #[test]
+    fn suffixes_prefer_exact_case_then_the_last_given() {
+        let colors = LsColors::parse("*.c=1:*.C=2:*.z=3:*.z=4");
+        let file = |name| colors.classify(NodeKind::File, 0o644, 1, name);
+        assert_eq!(file("a.c"), Some("1"));
+        assert_eq!(file("a.C"), Some("2"));
+        assert_eq!(file("a.Z"), Some("4"));
+        assert_eq!(file("a.h"), None);
+    }
+
+    #[test]
+    fn other_kinds_by_type_key() {
+        let colors = LsColors::default();
+        assert_eq!(colors.classify(NodeKind::Symlink, 0, 1, "l"), Some("01;36"));
+        assert_eq!(colors.classify(NodeKind::Fifo, 0, 1, "p"), Some("40;33"));
+        assert_eq!(colors.classify(NodeKind::Socket, 0, 1, "s"), Some("01;35"));
+        assert_eq!(
+            colors.classify(NodeKind::CharDevice, 0, 1, "c"),
+            Some("40;33;01")
+        );
+        assert_eq!(colors.classify(NodeKind::Other, 0, 1, "o"), None);
+    }
+
+    #[test]
+    fn paint_wraps_in_escapes() {
+        assert_eq!(paint(Some("01;34"), "src"), "\x1b[01;34msrc\x1b[0m");
+        assert_eq!(paint(None, "src"), "src");
+    }
+}
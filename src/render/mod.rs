@@ -0,0 +1,288 @@
+//! Renderers turning a [`Tree`] into output.
+
+mod columns;
+mod html;
+mod json;
+mod jsonl;
+mod ls_colors;
+mod owners;
+mod template;
+mod text;
+mod xml;
+
+pub use columns::{Columns, SizeFormat, TimeColumn, TimeField, TimeStyle};
+pub use html::HtmlRenderer;
+pub use json::JsonRenderer;
+pub use jsonl::JsonLinesRenderer;
+pub use ls_colors::LsColors;
+pub use template::{Template, TemplateError};
+pub use text::TextRenderer;
+pub use xml::XmlRenderer;
+
+use crate::tree::{Node, NodeKind, Omitted, Report, Tree};
+use std::env;
+use std::io::{self, Write};
+
+/// Writes whole [`Tree`]s in some output format.
+pub trait Render {
+    /// Writes the trees one after the other, with a single report totaling them.
+    fn render_all(&self, trees: &[Tree], out: &mut dyn Write) -> io::Result<()>;
+
+    fn render(&self, tree: &Tree, out: &mut dyn Write) -> io::Result<()> {
+        self.render_all(std::slice::from_ref(tree), out)
+    }
+}
+
+/// The output formats available on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Format {
+    /// Indented tree with branch glyphs
+    #[default]
+    Text,
+    /// Nested document compatible with `tree -J`
+    Json,
+    /// One JSON object per entry, streamed while walking
+    Jsonl,
+    /// XML document compatible with `tree -X`
+    Xml,
+    /// Self-contained page with collapsible directories
+    Html,
+}
+
+/// When the text output is colored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ColorMode {
+    /// Only on a terminal, unless `NO_COLOR` or `CLICOLOR_FORCE` is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// Whether to color output going to a terminal, or elsewhere if `terminal` is false.
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                // See https://no-color.org and https://bixense.com/clicolors
+                if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+                    false
+                } else {
+                    env::var_os("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0")
+                        || terminal
+                }
+            }
+        }
+    }
+}
+
+/// Name of the entry type, using the same words as GNU tree
+pub(crate) fn type_name(kind: NodeKind) -> &'static str {
+    match kind {
+        NodeKind::Directory => "directory",
+        NodeKind::File => "file",
+        NodeKind::Symlink => "link",
+        NodeKind::Fifo => "fifo",
+        NodeKind::Socket => "socket",
+        NodeKind::BlockDevice => "blockdev",
+        NodeKind::CharDevice => "chardev",
+        NodeKind::Other => "other",
+    }
+}
+
+/// Quotes and escapes `s` as a JSON string.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Escapes the characters that are special in XML and HTML text and attributes.
+pub(crate) fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The closing line of the text and HTML outputs, e.g. `3 directories, 12 files, 48K total`.
+///
+/// Symlinks, other files and errors are only mentioned when there are any.
+pub(crate) fn summary(report: &Report, size: Option<SizeFormat>) -> String {
+    let mut parts = vec![
+        plural(report.directories, "directory", "directories"),
+        plural(report.files, "file", "files"),
+    ];
+    if report.broken > 0 {
+        parts.push(format!(
+            "{} ({} broken)",
+            plural(report.symlinks, "symlink", "symlinks"),
+            report.broken
+        ));
+    } else if report.symlinks > 0 {
+        parts.push(plural(report.symlinks, "symlink", "symlinks"));
+    }
+    if report.others > 0 {
+        parts.push(plural(report.others, "other file", "other files"));
+    }
+    if report.errors > 0 {
+        parts.push(plural(report.errors, "error", "errors"));
+    }
+    match size {
+        Some(SizeFormat::Bytes) => parts.push(format!("{} bytes", report.bytes)),
+        Some(format) => parts.push(format!("{} total", format.format(report.bytes))),
+        None => {}
+    }
+    parts.join(", ")
+}
+
+/// Follows `node` down through directories holding nothing but another
+/// directory, returning a childless stand-in for the chain named like `a/b/c/`
+/// and the directory it ends with; `None` if there is no such chain.
+///
+/// The root never starts a chain, and neither do links or unreadable directories.
+pub(crate) fn compact_chain(node: &Node) -> Option<(Node, &Node)> {
+    // Only the first name is kept whole, the others may be full paths too
+    let mut names = vec![node.name.clone()];
+    let mut last = node;
+    while let [child] = last.children.as_slice() {
+        let plain = |node: &Node| node.link_target.is_none() && node.error.is_none();
+        if last.depth == 0 || last.omitted.is_some() || !plain(last) {
+            break;
+        }
+        if !child.is_dir() || child.link_target.is_some() {
+            break;
+        }
+        let name = child.path.file_name().unwrap_or(child.path.as_os_str());
+        names.push(name.to_string_lossy().into_owned());
+        last = child;
+    }
+    if names.len() < 2 {
+        return None;
+    }
+
+    let stand_in = Node {
+        name: format!("{}/", names.join("/")),
+        path: last.path.clone(),
+        depth: last.depth,
+        kind: last.kind,
+        link_target: None,
+        link_chain: Vec::new(),
+        broken: false,
+        metadata: last.metadata.clone(),
+        error: last.error.clone(),
+        children: Vec::new(),
+        omitted: None,
+    };
+    Some((stand_in, last))
+}
+
+/// The line standing for the children left out of a directory.
+pub(crate) fn omitted_line(omitted: &Omitted, size: Option<SizeFormat>) -> String {
+    format!(
+        "\u{2026} {} more {} ({})",
+        omitted.entries,
+        if omitted.entries == 1 {
+            "entry"
+        } else {
+            "entries"
+        },
+        size.unwrap_or_default().format_long(omitted.bytes)
+    )
+}
+
+fn plural(count: usize, one: &str, many: &str) -> String {
+    format!("{} {}", count, if count == 1 { one } else { many })
+}
+
+/// The members of the JSON report object, shared by the JSON and JSON Lines outputs.
+pub(crate) fn json_report(report: &Report) -> String {
+    format!(
+        "\"type\":\"report\",\"directories\":{},\"files\":{},\"symlinks\":{},\"broken\":{},\"others\":{},\"bytes\":{},\"errors\":{}",
+        report.directories,
+        report.files,
+        report.symlinks,
+        report.broken,
+        report.others,
+        report.bytes,
+        report.errors
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::NodeKind::{Directory, File};
+    use std::path::Path;
+
+    /// `src/main/java/app/Main.java` with `src` below the root, named with `name`.
+    fn chain(name: impl Fn(&str) -> String) -> Node {
+        let mut node = Node::stub("root/src/main/java/app/Main.java", File, vec![]);
+        for dir in [
+            "root/src/main/java/app",
+            "root/src/main/java",
+            "root/src/main",
+            "root/src",
+        ] {
+            node = Node::stub(dir, Directory, vec![node]);
+        }
+        rename(&mut node, &name);
+        node
+    }
+
+    fn rename(node: &mut Node, name: &impl Fn(&str) -> String) {
+        node.name = name(&node.path.to_string_lossy());
+        for child in &mut node.children {
+            rename(child, name);
+        }
+    }
+
+    #[test]
+    fn compact_chain_joins_file_names() {
+        let src = chain(|path| path.rsplit('/').next().unwrap().to_string());
+        let (stand_in, last) = compact_chain(&src).unwrap();
+        assert_eq!(stand_in.name, "src/main/java/app/");
+        assert_eq!(last.path, Path::new("root/src/main/java/app"));
+        assert_eq!(stand_in.depth, 4);
+    }
+
+    #[test]
+    fn compact_chain_with_full_paths() {
+        // As named by `-f`, or `--absolute` with another start
+        let src = chain(|path| path.to_string());
+        let (stand_in, _) = compact_chain(&src).unwrap();
+        assert_eq!(stand_in.name, "root/src/main/java/app/");
+    }
+
+    #[test]
+    fn no_chain_without_a_single_directory() {
+        let src = chain(|path| path.to_string());
+        let app = &src.children[0].children[0].children[0];
+        assert!(compact_chain(app).is_none());
+
+        let root = Node::stub("root", Directory, vec![src.clone()]);
+        assert!(compact_chain(&root).is_none());
+    }
+}
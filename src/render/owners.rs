@@ -0,0 +1,88 @@
+//! User and group names looked up in the system database, each id only once.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+type Cache = Mutex<HashMap<u32, Option<String>>>;
+
+static USERS: OnceLock<Cache> = OnceLock::new();
+static GROUPS: OnceLock<Cache> = OnceLock::new();
+
+/// Name of the user `uid`, or the number itself if it has none.
+pub(crate) fn user_name(uid: u32) -> String {
+    cached(&USERS, uid, lookup::user)
+}
+
+/// Name of the group `gid`, or the number itself if it has none.
+pub(crate) fn group_name(gid: u32) -> String {
+    cached(&GROUPS, gid, lookup::group)
+}
+
+fn cached(cache: &OnceLock<Cache>, id: u32, lookup: fn(u32) -> Option<String>) -> String {
+    let mut cache = cache
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache
+        .entry(id)
+        .or_insert_with(|| lookup(id))
+        .clone()
+        .unwrap_or_else(|| id.to_string())
+}
+
+#[cfg(unix)]
+mod lookup {
+    use std::ffi::{c_char, CStr};
+    use std::{mem, ptr};
+
+    /// Largest buffer offered for the strings of an entry
+    const MAX_BUFFER: usize = 1 << 20;
+
+    pub(super) fn user(uid: u32) -> Option<String> {
+        let mut buf = vec![0 as c_char; 1024];
+        loop {
+            // SAFETY: getpwuid_r only writes to `entry` and `buf`, and the
+            // name pointing into `buf` is copied before it is reused
+            unsafe {
+                let mut entry: libc::passwd = mem::zeroed();
+                let mut result = ptr::null_mut();
+                match libc::getpwuid_r(uid, &mut entry, buf.as_mut_ptr(), buf.len(), &mut result) {
+                    libc::ERANGE if buf.len() < MAX_BUFFER => buf.resize(buf.len() * 4, 0),
+                    0 if !result.is_null() => return Some(to_string(entry.pw_name)),
+                    _ => return None,
+                }
+            }
+        }
+    }
+
+    pub(super) fn group(gid: u32) -> Option<String> {
+        let mut buf = vec![0 as c_char; 1024];
+        loop {
+            // SAFETY: as for `user`
+            unsafe {
+                let mut entry: libc::group = mem::zeroed();
+                let mut result = ptr::null_mut();
+                match libc::getgrgid_r(gid, &mut entry, buf.as_mut_ptr(), buf.len(), &mut result) {
+                    libc::ERANGE if buf.len() < MAX_BUFFER => buf.resize(buf.len() * 4, 0),
+                    0 if !result.is_null() => return Some(to_string(entry.gr_name)),
+                    _ => return None,
+                }
+            }
+        }
+    }
+
+    unsafe fn to_string(name: *const c_char) -> String {
+        CStr::from_ptr(name).to_string_lossy().into_owned()
+    }
+}
+
+#[cfg(not(unix))]
+mod lookup {
+    pub(super) fn user(_uid: u32) -> Option<String> {
+        None
+    }
+
+    pub(super) fn group(_gid: u32) -> Option<String> {
+        None
+    }
+}
@@ -0,0 +1,489 @@
+//! User-defined lines for the text output, such as
+//! `{prefix}{name}{?link: -> {target}} [{size:h} {mtime:%F} {mode}]`.
+
+use super::columns::{octal_mode, symbolic_mode};
+use super::owners::{group_name, user_name};
+use super::{type_name, LsColors, SizeFormat, TimeColumn, TimeField, TimeStyle};
+use crate::tree::{Node, NodeKind};
+use std::fmt;
+
+const FIELDS: &str = "prefix, name, path, target, type, depth, size, mtime, atime, ctime, \
+                      btime, mode, octal, user, group, links, error";
+const CONDITIONS: &str = "link, dir, file, broken, error";
+
+/// A parsed line template.
+///
+/// `{field}` or `{field:spec}` is replaced by a field of the entry, and
+/// `{?condition:text}` writes `text`, which may hold placeholders, only when
+/// the condition holds. `{{` and `}}` write literal braces.
+///
+/// Sizes take `b` (the default), `h` or `si` like `-h` and `--si`. Times
+/// take `iso`, `rfc3339`, `relative` or a strftime format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    Text(String),
+    Field(Field),
+    If(Condition, Vec<Item>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Field {
+    Prefix,
+    Name,
+    Path,
+    Target,
+    Type,
+    Depth,
+    Size(SizeFormat),
+    Time(TimeField, TimeStyle, Option<String>),
+    Mode,
+    Octal,
+    User,
+    Group,
+    Links,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Condition {
+    Link,
+    Dir,
+    File,
+    Broken,
+    Error,
+}
+
+/// Why a template could not be parsed, with the byte offset of the problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at offset {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// What a template needs besides the entry itself.
+pub(crate) struct Context<'a> {
+    /// Branch glyphs drawn before the entry
+    pub prefix: &'a str,
+    /// Colors for names and targets, `None` to write them plain
+    pub colors: Option<&'a LsColors>,
+    pub utc: bool,
+}
+
+impl Template {
+    /// Parses `template`, rejecting unknown placeholders, specs and conditions.
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        let mut parser = Parser {
+            source: template,
+            offset: 0,
+        };
+        let items = parser.items(false)?;
+        Ok(Template { items })
+    }
+
+    /// Writes the line for `node`, without the line break.
+    pub(crate) fn render(&self, node: &Node, context: &Context) -> String {
+        let mut line = String::new();
+        render_items(&self.items, node, context, &mut line);
+        line
+    }
+}
+
+fn render_items(items: &[Item], node: &Node, context: &Context, line: &mut String) {
+    for item in items {
+        match item {
+            Item::Text(text) => line.push_str(text),
+            Item::Field(field) => line.push_str(&field_value(field, node, context)),
+            Item::If(condition, items) => {
+                let holds = match condition {
+                    Condition::Link => node.link_target.is_some() || node.is_symlink(),
+                    Condition::Dir => node.is_dir(),
+                    Condition::File => node.kind == NodeKind::File,
+                    Condition::Broken => node.broken,
+                    Condition::Error => node.error.is_some(),
+                };
+                if holds {
+                    render_items(items, node, context, line);
+                }
+            }
+        }
+    }
+}
+
+/// The value of `field` for `node`, `?` where unknown and empty where it does not apply.
+fn field_value(field: &Field, node: &Node, context: &Context) -> String {
+    let metadata = node.metadata.as_ref();
+    let known = |value: Option<String>| value.unwrap_or_else(|| "?".to_string());
+    match field {
+        Field::Prefix => context.prefix.to_string(),
+        Field::Name => match context.colors {
+            Some(colors) => colors.paint_name(node),
+            None => node.name.clone(),
+        },
+        Field::Path => node.path.display().to_string(),
+        Field::Target => match (&node.link_target, context.colors) {
+            (Some(target), Some(colors)) => colors.paint_target(node, target),
+            (Some(target), None) => target.display().to_string(),
+            (None, _) => String::new(),
+        },
+        Field::Type => type_name(node.kind).to_string(),
+        Field::Depth => node.depth.to_string(),
+        Field::Size(format) => known(node.size().map(|size| format.format(size))),
+        Field::Time(field, style, strftime) => TimeColumn {
+            field: *field,
+            style: *style,
+            strftime: strftime.clone(),
+            utc: context.utc,
+        }
+        .format(node),
+        Field::Mode => known(metadata.and_then(|m| m.mode).map(symbolic_mode)),
+        Field::Octal => known(metadata.and_then(|m| m.mode).map(octal_mode)),
+        Field::User => known(metadata.and_then(|m| m.uid).map(user_name)),
+        Field::Group => known(metadata.and_then(|m| m.gid).map(group_name)),
+        Field::Links => known(metadata.map(|m| m.links.to_string())),
+        Field::Error => node.error.clone().unwrap_or_default(),
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    offset: usize,
+}
+
+impl Parser<'_> {
+    /// Parses up to the end, or up to the `}` closing a condition if `nested`.
+    fn items(&mut self, nested: bool) -> Result<Vec<Item>, TemplateError> {
+        let mut items = Vec::new();
+        let mut text = String::new();
+        loop {
+            let rest = &self.source[self.offset..];
+            let Some(c) = rest.chars().next() else {
+                if nested {
+                    return Err(self.error(self.offset, "missing '}' to close the condition"));
+                }
+                break;
+            };
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                text.push(c);
+                self.offset += 2;
+                continue;
+            }
+
+            let start = self.offset;
+            self.offset += c.len_utf8();
+            match c {
+                '}' if nested => break,
+                '}' => {
+                    return Err(self.error(start, "unmatched '}', write '}}' for a literal brace"))
+                }
+                '{' => {
+                    if !text.is_empty() {
+                        items.push(Item::Text(std::mem::take(&mut text)));
+                    }
+                    items.push(self.placeholder(start)?);
+                }
+                _ => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            items.push(Item::Text(text));
+        }
+        Ok(items)
+    }
+
+    /// Parses what follows the `{` at `start`.
+    fn placeholder(&mut self, start: usize) -> Result<Item, TemplateError> {
+        if let Some(rest) = self.source[self.offset..].strip_prefix('?') {
+            // The condition ends at the colon, which must come before any brace
+            let colon = match rest.find([':', '{', '}']) {
+                Some(end) if rest[end..].starts_with(':') => end,
+                _ => return Err(self.error(start, "missing ':' after the condition")),
+            };
+            let name = &rest[..colon];
+            let condition = match name {
+                "link" => Condition::Link,
+                "dir" => Condition::Dir,
+                "file" => Condition::File,
+                "broken" => Condition::Broken,
+                "error" => Condition::Error,
+                _ => {
+                    return Err(self.error(
+                        start,
+                        &format!(
+                            "unknown condition '{}', expected one of {}",
+                            name, CONDITIONS
+                        ),
+                    ))
+                }
+            };
+            self.offset += 1 + colon + 1;
+            return Ok(Item::If(condition, self.items(true)?));
+        }
+
+        let rest = &self.source[self.offset..];
+        let Some(end) = rest.find('}') else {
+            return Err(self.error(start, "missing '}' to close the placeholder"));
+        };
+        self.offset += end + 1;
+        let (name, spec) = match rest[..end].split_once(':') {
+            Some((name, spec)) => (name, Some(spec)),
+            None => (&rest[..end], None),
+        };
+        self.field(start, name, spec).map(Item::Field)
+    }
+
+    fn field(&self, start: usize, name: &str, spec: Option<&str>) -> Result<Field, TemplateError> {
+        let time = |field| {
+            let (style, strftime) = match spec {
+                None => (TimeStyle::Default, None),
+                Some("iso") => (TimeStyle::Iso, None),
+                Some("rfc3339") => (TimeStyle::Rfc3339, None),
+                Some("relative") => (TimeStyle::Relative, None),
+                Some(format) => match TimeColumn::check_strftime(format) {
+                    Ok(()) => (TimeStyle::Default, Some(format.to_string())),
+                    Err(err) => {
+                        return Err(self.error(start, &format!("{}: '{}'", err, format)));
+                    }
+                },
+            };
+            Ok(Field::Time(field, style, strftime))
+        };
+
+        let field = match name {
+            "size" => {
+                return match spec {
+                    None | Some("b") => Ok(Field::Size(SizeFormat::Bytes)),
+                    Some("h") => Ok(Field::Size(SizeFormat::Iec)),
+                    Some("si") => Ok(Field::Size(SizeFormat::Si)),
+                    Some(spec) => Err(self.error(
+                        start,
+                        &format!("unknown size format '{}', expected b, h or si", spec),
+                    )),
+                }
+            }
+            "mtime" => return time(TimeField::Mtime),
+            "atime" => return time(TimeField::Atime),
+            "ctime" => return time(TimeField::Ctime),
+            "btime" => return time(TimeField::Birth),
+            "prefix" => Field::Prefix,
+            "name" => Field::Name,
+            "path" => Field::Path,
+            "target" => Field::Target,
+            "type" => Field::Type,
+            "depth" => Field::Depth,
+            "mode" => Field::Mode,
+            "octal" => Field::Octal,
+            "user" => Field::User,
+            "group" => Field::Group,
+            "links" => Field::Links,
+            "error" => Field::Error,
+            _ => {
+                return Err(self.error(
+                    start,
+                    &format!(
+                        "unknown placeholder '{{{}}}', expected one of {}",
+                        name, FIELDS
+                    ),
+                ))
+            }
+        };
+        match spec {
+            Some(_) => Err(self.error(start, &format!("'{{{}}}' takes no format", name))),
+            None => Ok(field),
+        }
+    }
+
+    fn error(&self, offset: usize, message: &str) -> TemplateError {
+        TemplateError {
+            offset,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::Metadata;
+    use std::path::PathBuf;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    const EXAMPLE: &str = "{prefix}{name}{?link: -> {target}} [{size:h} {mtime:%F} {mode}]";
+
+    fn node(name: &str, kind: NodeKind, mode: u32) -> Node {
+        Node {
+            name: name.to_string(),
+            path: PathBuf::from("root").join(name),
+            depth: 1,
+            kind,
+            link_target: None,
+            link_chain: Vec::new(),
+            broken: false,
+            metadata: Some(Metadata {
+                size: 2048,
+                allocated: 4096,
+                modified: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+                accessed: None,
+                changed: None,
+                created: None,
+                links: 1,
+                file_id: None,
+                mode: Some(mode),
+                uid: None,
+                gid: None,
+            }),
+            error: None,
+            children: Vec::new(),
+            omitted: None,
+        }
+    }
+
+    fn render(template: &str, node: &Node) -> String {
+        let context = Context {
+            prefix: "├── ",
+            colors: None,
+            utc: true,
+        };
+        Template::parse(template).unwrap().render(node, &context)
+    }
+
+    fn error(template: &str) -> (usize, String) {
+        let err = Template::parse(template).unwrap_err();
+        (err.offset, err.message)
+    }
+
+    #[test]
+    fn example_template() {
+        let file = node("notes.txt", NodeKind::File, 0o100644);
+        assert_eq!(
+            render(EXAMPLE, &file),
+            "├── notes.txt [2.0K 2023-11-14 -rw-r--r--]"
+        );
+
+        let mut link = node("latest", NodeKind::Symlink, 0o120777);
+        link.link_target = Some(PathBuf::from("v1.2"));
+        assert_eq!(
+            render(EXAMPLE, &link),
+            "├── latest -> v1.2 [2.0K 2023-11-14 lrwxrwxrwx]"
+        );
+    }
+
+    #[test]
+    fn unknown_metadata_is_a_question_mark() {
+        let mut file = node("a", NodeKind::File, 0);
+        file.metadata = None;
+        file.error = Some("error reading metadata".to_string());
+        assert_eq!(
+            render(
+                "{name} {size} {mode} {mtime} {links}{?error: [{error}]}",
+                &file
+            ),
+            "a ? ? ? ? [error reading metadata]"
+        );
+    }
+
+    #[test]
+    fn specs() {
+        let file = node("a", NodeKind::File, 0o104755);
+        assert_eq!(render("{size}", &file), "2048");
+        assert_eq!(render("{size:b} {size:si}", &file), "2048 2.0k");
+        assert_eq!(render("{mtime:%H:%M:%S}", &file), "22:13:20");
+        assert_eq!(render("{mtime:iso}", &file), "2023-11-14T22:13:20");
+        assert_eq!(render("{mode} {octal}", &file), "-rwsr-xr-x 4755");
+        assert_eq!(render("{type} {depth} {path}", &file), "file 1 root/a");
+    }
+
+    #[test]
+    fn conditions() {
+        let template = "{?dir:[{name}/]}{?file:{name}}{?broken: (broken)}";
+        let dir = node("src", NodeKind::Directory, 0o040755);
+        assert_eq!(render(template, &dir), "[src/]");
+        assert_eq!(render(template, &node("a", NodeKind::File, 0)), "a");
+
+        let mut broken = node("gone", NodeKind::Symlink, 0o120777);
+        broken.broken = true;
+        assert_eq!(render(template, &broken), " (broken)");
+    }
+
+    #[test]
+    fn escaped_braces() {
+        let dir = node("src", NodeKind::Directory, 0o040755);
+        assert_eq!(render("{{{name}}}", &dir), "{src}");
+        assert_eq!(render("{?dir:{{{name}}}}", &dir), "{src}");
+        assert_eq!(render("{?file:{{x}}}after", &dir), "after");
+        assert_eq!(render("}}{{", &dir), "}{");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error("{name} {nmae}"),
+            (
+                7,
+                format!("unknown placeholder '{{nmae}}', expected one of {}", FIELDS)
+            )
+        );
+        assert_eq!(
+            error("{size:k}"),
+            (
+                0,
+                "unknown size format 'k', expected b, h or si".to_string()
+            )
+        );
+        assert_eq!(
+            error("{mtime:%Q}"),
+            (0, "not a valid strftime format: '%Q'".to_string())
+        );
+        assert_eq!(
+            error("{name:h}"),
+            (0, "'{name}' takes no format".to_string())
+        );
+        assert_eq!(
+            error("x{name"),
+            (1, "missing '}' to close the placeholder".to_string())
+        );
+        assert_eq!(
+            error("a}b"),
+            (
+                1,
+                "unmatched '}', write '}}' for a literal brace".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn condition_errors() {
+        assert_eq!(
+            error("{?exe:x}"),
+            (
+                0,
+                format!("unknown condition 'exe', expected one of {}", CONDITIONS)
+            )
+        );
+        let missing_colon = (0, "missing ':' after the condition".to_string());
+        assert_eq!(error("{?dir}"), missing_colon);
+        assert_eq!(error("{?dir}{size:h}"), missing_colon);
+        assert_eq!(error("{?dir{name}:x}"), missing_colon);
+        assert_eq!(
+            error("{?dir:{name}"),
+            (12, "missing '}' to close the condition".to_string())
+        );
+        // `}}` is a literal brace, so it cannot close the condition
+        assert_eq!(
+            error("{?dir:x}}"),
+            (9, "missing '}' to close the condition".to_string())
+        );
+    }
+}
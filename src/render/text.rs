@@ -0,0 +1,248 @@
+use super::template::Context;
+use super::{compact_chain, omitted_line, summary, Columns, LsColors, Render, Template};
+use crate::tree::{Node, Tree};
+use std::io::{self, Write};
+
+/// Renders the classic indented tree, with the metadata columns in
+/// brackets before each name like GNU tree.
+#[derive(Debug, Clone, Default)]
+pub struct TextRenderer {
+    /// Disable colors
+    pub no_color: bool,
+    /// Colors for the names, see [`LsColors::from_env`]
+    pub colors: LsColors,
+    /// Show every hop of chained symlinks
+    pub link_chains: bool,
+    /// Replaces the whole line of every entry, columns included
+    pub template: Option<Template>,
+    /// Show directories holding only another directory on one line, like `a/b/c/`
+    pub compact_chains: bool,
+    pub columns: Columns,
+    /// Leave out the closing line with the counts
+    pub no_report: bool,
+}
+
+// Line drawing used by GNU tree in UTF-8 locales, including its no-break spaces
+const VERTICAL: &str = "\u{2502}\u{a0}\u{a0} ";
+const BLANK: &str = "    ";
+const BRANCH: &str = "\u{251c}\u{2500}\u{2500} ";
+const CORNER: &str = "\u{2514}\u{2500}\u{2500} ";
+
+impl Render for TextRenderer {
+    fn render_all(&self, trees: &[Tree], out: &mut dyn Write) -> io::Result<()> {
+        let mut prefix = String::new();
+        for tree in trees {
+            // Like GNU tree, the root has no metadata columns
+            match &self.template {
+                Some(template) => writeln!(out, "{}", self.expand(template, "", &tree.root))?,
+                None => writeln!(out, "{}", self.display_name(&tree.root))?,
+            }
+            self.render_children(out, &tree.root, &mut prefix)?;
+        }
+
+        if !self.no_report {
+            let report = trees.iter().map(|tree| tree.report).sum();
+            writeln!(out, "\n{}", summary(&report, self.columns.size))?;
+        }
+        Ok(())
+    }
+}
+
+impl TextRenderer {
+    /// Writes the children of `node`, `prefix` holding the glyphs drawn for its ancestors.
+    fn render_children(
+        &self,
+        out: &mut dyn Write,
+        node: &Node,
+        prefix: &mut String,
+    ) -> io::Result<()> {
+        let count = node.children.len() + usize::from(node.omitted.is_some());
+        for (index, child) in node.children.iter().enumerate() {
+            let is_last = index + 1 == count;
+            let branch = if is_last { CORNER } else { BRANCH };
+            let chain = self.compact_chains.then(|| compact_chain(child)).flatten();
+            let (shown, child) = match &chain {
+                Some((stand_in, last)) => (stand_in, *last),
+                None => (child, child),
+            };
+            self.write_line(out, &format!("{}{}", prefix, branch), shown)?;
+
+            if !child.children.is_empty() || child.omitted.is_some() {
+                let len = prefix.len();
+                prefix.push_str(if is_last { BLANK } else { VERTICAL });
+                self.render_children(out, child, prefix)?;
+                prefix.truncate(len);
+            }
+        }
+        if let Some(omitted) = &node.omitted {
+            let line = omitted_line(omitted, self.columns.size);
+            writeln!(out, "{}{}{}", prefix, CORNER, line)?;
+        }
+        Ok(())
+    }
+
+    fn write_line(&self, out: &mut dyn Write, prefix: &str, node: &Node) -> io::Result<()> {
+        if let Some(template) = &self.template {
+            return writeln!(out, "{}", self.expand(template, prefix, node));
+        }
+
+        let mut fields = Vec::new();
+        for (column, value) in self.columns.owner_fields(node) {
+            fields.push(match column {
+                // Names are padded like GNU tree does
+                "user" | "group" => format!("{:<8}", value),
+                _ => value,
+            });
+        }
+        if let Some(size) = self.columns.size {
+            let formatted = node
+                .size()
+                .map_or_else(|| "?".to_string(), |bytes| size.format(bytes));
+            fields.push(format!("{:>1$}", formatted, size.width()));
+        }
+        if let Some(time) = &self.columns.time {
+            fields.push(time.format(node));
+        }
+
+        let name = self.display_name(node);
+        if fields.is_empty() {
+            writeln!(out, "{}{}", prefix, name)
+        } else {
+            writeln!(out, "{}[{}]  {}", prefix, fields.join(" "), name)
+        }
+    }
+
+    fn expand(&self, template: &Template, prefix: &str, node: &Node) -> String {
+        let context = Context {
+            prefix,
+            colors: (!self.no_color).then_some(&self.colors),
+            utc: self.columns.time.as_ref().is_some_and(|time| time.utc),
+        };
+        template.render(node, &context)
+    }
+
+    fn display_name(&self, node: &Node) -> String {
+        let mut name = if self.no_color {
+            node.name.clone()
+        } else {
+            self.colors.paint_name(node)
+        };
+
+        // Append symlink target if applicable
+        if let Some(first) = &node.link_target {
+            let chain: &[_] = if self.link_chains {
+                &node.link_chain
+            } else {
+                &[]
+            };
+            let (target, hops) = match chain.split_last() {
+                Some((last, hops)) => (last, [first].into_iter().chain(hops).collect()),
+                None => (first, Vec::new()),
+            };
+            for hop in hops {
+                name.push_str(&format!(" -> {}", hop.display()));
+            }
+            if self.no_color {
+                name.push_str(&format!(" -> {}", target.display()));
+            } else {
+                name.push_str(&format!(" -> {}", self.colors.paint_target(node, target)));
+            }
+            if node.broken {
+                name.push_str(" [broken]");
+            }
+        } else if node.is_symlink() {
+            name.push_str(" -> [unresolved]");
+        }
+        if let Some(error) = &node.error {
+            name.push_str(&format!(" [{}]", error));
+        }
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{NodeKind, Omitted, Report};
+
+    fn render(root: Node) -> String {
+        let renderer = TextRenderer {
+            no_color: true,
+            columns: Columns {
+                size: None,
+                time: None,
+                ..Columns::default()
+            },
+            ..TextRenderer::default()
+        };
+        let tree = Tree {
+            root,
+            errors: Vec::new(),
+            report: Report {
+                directories: 3,
+                files: 7,
+                ..Report::default()
+            },
+        };
+        let mut out = Vec::new();
+        renderer.render(&tree, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn glyphs_match_gnu_tree() {
+        use NodeKind::{Directory, File};
+
+        let mut last = Node::stub(
+            "root/c",
+            Directory,
+            vec![Node::stub("root/c/w", File, vec![])],
+        );
+        last.omitted = Some(Omitted {
+            entries: 3,
+            bytes: 2048,
+        });
+        let root = Node::stub(
+            "root",
+            Directory,
+            vec![
+                Node::stub(
+                    "root/a",
+                    Directory,
+                    vec![Node::stub(
+                        "root/a/b",
+                        Directory,
+                        vec![Node::stub("root/a/b/x", File, vec![])],
+                    )],
+                ),
+                Node::stub(
+                    "root/b",
+                    Directory,
+                    vec![
+                        Node::stub("root/b/y", File, vec![]),
+                        Node::stub("root/b/z", File, vec![]),
+                    ],
+                ),
+                Node::stub("root/f", File, vec![]),
+                last,
+            ],
+        );
+
+        let expected = "\
+root
+\u{251c}\u{2500}\u{2500} a
+\u{2502}\u{a0}\u{a0} \u{2514}\u{2500}\u{2500} b
+\u{2502}\u{a0}\u{a0}     \u{2514}\u{2500}\u{2500} x
+\u{251c}\u{2500}\u{2500} b
+\u{2502}\u{a0}\u{a0} \u{251c}\u{2500}\u{2500} y
+\u{2502}\u{a0}\u{a0} \u{2514}\u{2500}\u{2500} z
+\u{251c}\u{2500}\u{2500} f
+\u{2514}\u{2500}\u{2500} c
+    \u{251c}\u{2500}\u{2500} w
+    \u{2514}\u{2500}\u{2500} \u{2026} 3 more entries (2.0 KiB)
+
+3 directories, 7 files
+";
+        assert_eq!(render(root), expected);
+    }
+}
@@ -0,0 +1,104 @@
+use super::{type_name, xml_escape, Columns, Render, TimeColumn, TimeStyle};
+use crate::tree::{Node, Report, Tree};
+use std::io::{self, Write};
+
+/// Renders an XML document shaped like the output of GNU `tree -X`.
+///
+/// Every entry is an element named after its type, with the enabled metadata
+/// columns as attributes, and the document ends with a `<report>` element.
+#[derive(Debug, Clone, Default)]
+pub struct XmlRenderer {
+    pub columns: Columns,
+    /// Leave out the `<report>` element
+    pub no_report: bool,
+}
+
+impl Render for XmlRenderer {
+    fn render_all(&self, trees: &[Tree], out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(out, "<tree>")?;
+
+        // Like GNU tree, both forms of the permissions or neither, and times
+        // always machine-readable
+        let permissions = self.columns.permissions || self.columns.octal;
+        let columns = Columns {
+            permissions,
+            octal: permissions,
+            time: self.columns.time.clone().map(|time| TimeColumn {
+                style: TimeStyle::Rfc3339,
+                strftime: None,
+                ..time
+            }),
+            ..self.columns.clone()
+        };
+        for tree in trees {
+            write_node(out, &tree.root, 1, &columns)?;
+        }
+
+        if !self.no_report {
+            let report: Report = trees.iter().map(|tree| tree.report).sum();
+            writeln!(out, "  <report>")?;
+            writeln!(out, "    <directories>{}</directories>", report.directories)?;
+            writeln!(out, "    <files>{}</files>", report.files)?;
+            writeln!(out, "    <symlinks>{}</symlinks>", report.symlinks)?;
+            writeln!(out, "    <broken>{}</broken>", report.broken)?;
+            writeln!(out, "    <others>{}</others>", report.others)?;
+            writeln!(out, "    <bytes>{}</bytes>", report.bytes)?;
+            writeln!(out, "    <errors>{}</errors>", report.errors)?;
+            writeln!(out, "  </report>")?;
+        }
+        writeln!(out, "</tree>")
+    }
+}
+
+fn write_node(out: &mut dyn Write, node: &Node, level: usize, columns: &Columns) -> io::Result<()> {
+    let indent = "  ".repeat(level);
+    let tag = type_name(node.kind);
+    write!(
+        out,
+        "{}<{} name=\"{}\"",
+        indent,
+        tag,
+        xml_escape(&node.name)
+    )?;
+    if let Some(target) = &node.link_target {
+        write!(out, " target=\"{}\"", xml_escape(&target.to_string_lossy()))?;
+    }
+    if node.broken {
+        write!(out, " broken=\"true\"")?;
+    }
+
+    for (column, value) in columns.owner_fields(node) {
+        write!(out, " {}=\"{}\"", column, xml_escape(&value))?;
+    }
+
+    // Sizes are always in bytes, as with GNU tree
+    if let Some(size) = node.size().filter(|_| columns.size.is_some()) {
+        write!(out, " size=\"{}\"", size)?;
+    }
+    if let Some(time) = &columns.time {
+        if time.field.of(node).is_some() {
+            write!(out, " time=\"{}\"", time.format(node))?;
+        }
+    }
+    if let Some(error) = &node.error {
+        write!(out, " error=\"{}\"", xml_escape(error))?;
+    }
+    if let Some(omitted) = &node.omitted {
+        write!(
+            out,
+            " omitted=\"{}\" omitted_bytes=\"{}\"",
+            omitted.entries, omitted.bytes
+        )?;
+    }
+    write!(out, ">")?;
+
+    if node.is_dir() {
+        writeln!(out)?;
+        for child in &node.children {
+            write_node(out, child, level + 1, columns)?;
+        }
+        write!(out, "{}", indent)?;
+    }
+    writeln!(out, "</{}>", tag)
+}
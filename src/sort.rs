@@ -0,0 +1,311 @@
+//! Ordering of the entries within each directory.
+
+use crate::tree::Node;
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use walkdir::DirEntry;
+
+/// The key entries of a directory are sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SortBy {
+    /// Byte-wise by name
+    #[default]
+    Name,
+    /// By name, with digit runs compared numerically (`file2` before `file10`)
+    Version,
+    /// Largest first
+    Size,
+    /// Oldest modification time first
+    Mtime,
+    /// Oldest status change time first
+    Ctime,
+    /// By extension, then by name
+    #[value(name = "ext")]
+    Extension,
+    /// Leave entries in the order the filesystem returns them
+    None,
+}
+
+/// Whether directories are grouped before or after the other entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DirOrder {
+    #[default]
+    Mixed,
+    DirsFirst,
+    FilesFirst,
+}
+
+/// How entries are ordered within each directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sort {
+    pub by: SortBy,
+    /// Reverse the order given by `by`; directory grouping is kept
+    pub reverse: bool,
+    pub dir_order: DirOrder,
+}
+
+impl Sort {
+    /// True when the order would differ from the one returned by the filesystem
+    pub fn is_sorted(&self) -> bool {
+        self.by != SortBy::None || self.dir_order != DirOrder::Mixed
+    }
+
+    /// Compares two entries of the same directory, reading their metadata
+    /// each time; see [`Sort::comparator`] for one that reads it once.
+    pub fn compare(&self, a: &DirEntry, b: &DirEntry) -> Ordering {
+        self.compare_keys(a, b, self.key(a), self.key(b))
+    }
+
+    /// Returns a comparison for [`walkdir::WalkDir::sort_by`] that reads the
+    /// metadata of each entry only once, keeping the keys of the directory
+    /// being sorted.
+    pub fn comparator(self) -> impl FnMut(&DirEntry, &DirEntry) -> Ordering + Send + Sync {
+        let mut keys = KeyCache::default();
+        move |a, b| {
+            let (a_key, b_key) = (keys.get(&self, a), keys.get(&self, b));
+            self.compare_keys(a, b, a_key, b_key)
+        }
+    }
+
+    fn compare_keys(&self, a: &DirEntry, b: &DirEntry, a_key: Key, b_key: Key) -> Ordering {
+        let (a_dir, b_dir) = (a.file_type().is_dir(), b.file_type().is_dir());
+        self.arrange(a_dir, b_dir, a.file_name(), b.file_name(), || {
+            match self.by {
+                SortBy::Name | SortBy::None => Ordering::Equal,
+                SortBy::Version => version_cmp(
+                    a.file_name().as_encoded_bytes(),
+                    b.file_name().as_encoded_bytes(),
+                ),
+                SortBy::Size | SortBy::Mtime | SortBy::Ctime => a_key.cmp(&b_key),
+                SortBy::Extension => extension(a).cmp(&extension(b)),
+            }
+        })
+    }
+
+    /// The metadata `entry` is sorted by, `Key::Name` if only its name matters
+    fn key(&self, entry: &DirEntry) -> Key {
+        match self.by {
+            SortBy::Size => Key::Size(Reverse(size(entry))),
+            SortBy::Mtime => Key::Time(modified(entry)),
+            SortBy::Ctime => Key::Time(changed(entry)),
+            SortBy::Name | SortBy::Version | SortBy::Extension | SortBy::None => Key::Name,
+        }
+    }
+
+    /// Compares nodes by what the walk read of them, for orders only known
+    /// once it is over: directories totalled by [`crate::TreeBuilder::du`],
+    /// or the flat list of [`crate::TreeBuilder::files_only`] taken as a whole.
+    pub(crate) fn compare_nodes(&self, a: &Node, b: &Node) -> Ordering {
+        let (a_name, b_name) = (OsStr::new(&a.name), OsStr::new(&b.name));
+        self.arrange(a.is_dir(), b.is_dir(), a_name, b_name, || match self.by {
+            SortBy::Name | SortBy::None => Ordering::Equal,
+            SortBy::Version => version_cmp(a.name.as_bytes(), b.name.as_bytes()),
+            SortBy::Size => b.size().unwrap_or(0).cmp(&a.size().unwrap_or(0)),
+            SortBy::Mtime => a.modified().cmp(&b.modified()),
+            SortBy::Ctime => {
+                let changed = |node: &Node| node.metadata.as_ref().and_then(|m| m.changed);
+                changed(a).cmp(&changed(b))
+            }
+            SortBy::Extension => Path::new(a_name)
+                .extension()
+                .cmp(&Path::new(b_name).extension()),
+        })
+    }
+
+    /// Groups directories, then orders by `key` and the name, reversed if asked.
+    fn arrange<F>(
+        &self,
+        a_dir: bool,
+        b_dir: bool,
+        a_name: &OsStr,
+        b_name: &OsStr,
+        key: F,
+    ) -> Ordering
+    where
+        F: FnOnce() -> Ordering,
+    {
+        let grouping = match self.dir_order {
+            DirOrder::Mixed => Ordering::Equal,
+            DirOrder::DirsFirst => b_dir.cmp(&a_dir),
+            DirOrder::FilesFirst => a_dir.cmp(&b_dir),
+        };
+        if grouping != Ordering::Equal || self.by == SortBy::None {
+            return grouping;
+        }
+
+        // Fall back to the name so the order never depends on the filesystem
+        let ordering = key().then_with(|| a_name.as_encoded_bytes().cmp(b_name.as_encoded_bytes()));
+
+        if self.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+/// What an entry is sorted by besides its name; sizes are reversed so the
+/// largest comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+    Name,
+    Size(Reverse<u64>),
+    Time(Option<SystemTime>),
+}
+
+/// The keys of the entries of the directory being sorted, as walkdir sorts
+/// one directory at a time.
+#[derive(Default)]
+struct KeyCache {
+    dir: PathBuf,
+    keys: HashMap<OsString, Key>,
+}
+
+impl KeyCache {
+    fn get(&mut self, sort: &Sort, entry: &DirEntry) -> Key {
+        if !matches!(sort.by, SortBy::Size | SortBy::Mtime | SortBy::Ctime) {
+            return Key::Name;
+        }
+        let dir = entry.path().parent().unwrap_or(Path::new(""));
+        if dir != self.dir {
+            self.keys.clear();
+            self.dir = dir.to_path_buf();
+        }
+        if let Some(key) = self.keys.get(entry.file_name()) {
+            return *key;
+        }
+        let key = sort.key(entry);
+        self.keys.insert(entry.file_name().to_owned(), key);
+        key
+    }
+}
+
+/// Compares names so that runs of digits are ordered by their numeric value,
+/// e.g. `file2` before `file10`, independently of the locale.
+///
+/// Names equal but for leading zeros, like `a01` and `a1`, fall back to
+/// byte order, so only identical names compare equal.
+pub fn version_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    numeric_cmp(a, b).then_with(|| a.cmp(b))
+}
+
+fn numeric_cmp(mut a: &[u8], mut b: &[u8]) -> Ordering {
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (a_digits, a_rest) = split_digits(a);
+                let (b_digits, b_rest) = split_digits(b);
+                let a_num = trim_leading_zeros(a_digits);
+                let b_num = trim_leading_zeros(b_digits);
+                let ordering = a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a = a_rest;
+                b = b_rest;
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(y);
+                }
+                a = &a[1..];
+                b = &b[1..];
+            }
+        }
+    }
+}
+
+fn split_digits(s: &[u8]) -> (&[u8], &[u8]) {
+    let end = s
+        .iter()
+        .position(|c| !c.is_ascii_digit())
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+fn trim_leading_zeros(s: &[u8]) -> &[u8] {
+    let start = s.iter().position(|&c| c != b'0').unwrap_or(s.len());
+    &s[start..]
+}
+
+fn size(entry: &DirEntry) -> u64 {
+    entry.metadata().map(|m| m.len()).unwrap_or(0)
+}
+
+fn modified(entry: &DirEntry) -> Option<SystemTime> {
+    entry.metadata().ok()?.modified().ok()
+}
+
+fn changed(entry: &DirEntry) -> Option<SystemTime> {
+    change_time(&entry.metadata().ok()?)
+}
+
+fn extension(entry: &DirEntry) -> Option<&[u8]> {
+    Path::new(entry.file_name())
+        .extension()
+        .map(|ext| ext.as_encoded_bytes())
+}
+
+/// Time of the last status change, or the creation time where there is no such thing
+#[cfg(unix)]
+pub(crate) fn change_time(metadata: &fs::Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let seconds = Duration::from_secs(metadata.ctime().unsigned_abs());
+    let whole = if metadata.ctime() >= 0 {
+        UNIX_EPOCH.checked_add(seconds)
+    } else {
+        UNIX_EPOCH.checked_sub(seconds)
+    };
+    whole?.checked_add(Duration::from_nanos(metadata.ctime_nsec() as u64))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn change_time(metadata: &fs::Metadata) -> Option<SystemTime> {
+    metadata.created().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmp(a: &str, b: &str) -> Ordering {
+        version_cmp(a.as_bytes(), b.as_bytes())
+    }
+
+    #[test]
+    fn digit_runs_compare_numerically() {
+        assert_eq!(cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(cmp("file10", "file2"), Ordering::Greater);
+        assert_eq!(cmp("v1.9.0", "v1.10.0"), Ordering::Less);
+        assert_eq!(cmp("99", "100"), Ordering::Less);
+        assert_eq!(cmp("file2", "file2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn leading_zeros_break_ties_bytewise() {
+        assert_eq!(cmp("a01", "a2"), Ordering::Less);
+        assert_eq!(cmp("a010", "a9"), Ordering::Greater);
+        assert_eq!(cmp("a01", "a1"), Ordering::Less);
+        assert_eq!(cmp("a1", "a01"), Ordering::Greater);
+        assert_eq!(cmp("a01b", "a1a"), Ordering::Greater);
+    }
+
+    #[test]
+    fn digits_and_letters_compare_bytewise() {
+        assert_eq!(cmp("a1", "ab"), Ordering::Less);
+        assert_eq!(cmp("1a", "a"), Ordering::Less);
+        assert_eq!(cmp("file", "file1"), Ordering::Less);
+        assert_eq!(cmp("file1", "file"), Ordering::Greater);
+        assert_eq!(cmp("x9y", "x10a"), Ordering::Less);
+        assert_eq!(cmp("B1", "a1"), Ordering::Less);
+    }
+}
@@ -0,0 +1,247 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::AddAssign;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// The type of a filesystem entry, as seen without following symlinks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Directory,
+    File,
+    Symlink,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    Other,
+}
+
+/// Metadata captured for every node while walking.
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    /// Apparent size in bytes; see [`crate::TreeBuilder::du`] and
+    /// [`crate::TreeBuilder::allocated`] for what it holds for directories
+    pub size: u64,
+    /// Bytes actually allocated on disk, the apparent size where unknown
+    pub allocated: u64,
+    /// `None` where the platform does not record it, as for the other times
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    /// Last status change
+    pub changed: Option<SystemTime>,
+    /// Birth time, which many filesystems do not record
+    pub created: Option<SystemTime>,
+    /// Number of hard links to the entry
+    pub links: u64,
+    /// Device and inode numbers identifying the file, where the platform has them
+    pub file_id: Option<(u64, u64)>,
+    /// Unix `st_mode`, file type bits included
+    pub mode: Option<u32>,
+    /// Owning user id, where the platform has one
+    pub uid: Option<u32>,
+    /// Owning group id, where the platform has one
+    pub gid: Option<u32>,
+}
+
+/// A single entry of the tree, owning its children.
+#[derive(Debug, Clone)]
+pub struct Node {
+    /// File name of the entry, or the root path as given, converted lossily
+    /// to UTF-8; see [`crate::TreeBuilder::naming`] for the alternatives
+    pub name: String,
+    pub path: PathBuf,
+    /// Distance from the root, which is at depth 0
+    pub depth: usize,
+    pub kind: NodeKind,
+    /// Target of a symlink, `None` if unreadable or not a link. Followed
+    /// links keep their target, but take the kind of what it points to.
+    pub link_target: Option<PathBuf>,
+    /// Targets met after `link_target` while resolving a chain of symlinks,
+    /// see [`crate::TreeBuilder::link_chains`]
+    pub link_chain: Vec<PathBuf>,
+    /// A symlink whose final target does not exist
+    pub broken: bool,
+    /// `None` if the entry could not be read, see `error`
+    pub metadata: Option<Metadata>,
+    /// Why the entry or its contents could not be read, e.g. `error opening dir`
+    pub error: Option<String>,
+    pub children: Vec<Node>,
+    /// Children left out by [`crate::TreeBuilder::max_children`]
+    pub omitted: Option<Omitted>,
+}
+
+/// What the children left out of a directory held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Omitted {
+    pub entries: usize,
+    /// Total size of everything below them that is not a directory
+    pub bytes: u64,
+}
+
+impl Node {
+    pub fn is_dir(&self) -> bool {
+        self.kind == NodeKind::Directory
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.kind == NodeKind::Symlink
+    }
+
+    /// Size in bytes, if the metadata could be read
+    pub fn size(&self) -> Option<u64> {
+        self.metadata.as_ref().map(|m| m.size)
+    }
+
+    /// Iterate over this node and everything below it in pre-order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { stack: vec![self] }
+    }
+
+    /// Last modification time, if known
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.metadata.as_ref().and_then(|m| m.modified)
+    }
+}
+
+/// Counts of the entries below the root, as in GNU tree's final report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Report {
+    pub directories: usize,
+    /// Regular files only, unlike GNU tree which also counts symlinks here
+    pub files: usize,
+    pub symlinks: usize,
+    /// Symlinks whose target does not exist, also counted in `symlinks`
+    pub broken: usize,
+    /// Sockets, FIFOs, devices and the like
+    pub others: usize,
+    /// Total size of everything that is not a directory
+    pub bytes: u64,
+    /// Problems met while walking, each leaving part of the tree out
+    pub errors: usize,
+}
+
+impl Report {
+    /// Counts one more entry.
+    pub fn add(&mut self, node: &Node) {
+        match node.kind {
+            NodeKind::Directory => self.directories += 1,
+            NodeKind::File => self.files += 1,
+            NodeKind::Symlink => self.symlinks += 1,
+            NodeKind::Fifo
+            | NodeKind::Socket
+            | NodeKind::BlockDevice
+            | NodeKind::CharDevice
+            | NodeKind::Other => self.others += 1,
+        }
+        if node.broken {
+            self.broken += 1;
+        }
+        if let Some(metadata) = node.metadata.as_ref().filter(|_| !node.is_dir()) {
+            self.bytes += metadata.size;
+        }
+    }
+}
+
+impl AddAssign for Report {
+    fn add_assign(&mut self, other: Report) {
+        self.directories += other.directories;
+        self.files += other.files;
+        self.symlinks += other.symlinks;
+        self.broken += other.broken;
+        self.others += other.others;
+        self.bytes += other.bytes;
+        self.errors += other.errors;
+    }
+}
+
+/// Totals the reports of several trees.
+///
+/// Hard-linked files are only recognized within each tree, so one linked
+/// into several of them is counted in `bytes` once per tree.
+impl Sum for Report {
+    fn sum<I: Iterator<Item = Report>>(reports: I) -> Report {
+        let mut total = Report::default();
+        for report in reports {
+            total += report;
+        }
+        total
+    }
+}
+
+/// An owned directory tree produced by [`crate::TreeBuilder`].
+#[derive(Debug, Clone)]
+pub struct Tree {
+    pub root: Node,
+    /// Every problem met while walking, including those shown on the nodes
+    pub errors: Vec<WalkError>,
+    /// Counts of the entries below the root, taken by [`crate::TreeBuilder::build`]
+    /// before [`crate::TreeBuilder::max_children`] leaves any out. With
+    /// [`crate::TreeBuilder::du`], hard-linked files are counted once in `bytes`.
+    pub report: Report,
+}
+
+/// A problem met while walking, which left part of the tree out.
+#[derive(Debug, Clone)]
+pub struct WalkError {
+    pub path: Option<PathBuf>,
+    pub message: String,
+}
+
+impl fmt::Display for WalkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path.display(), self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl Tree {
+    /// Iterate over all nodes in pre-order, starting with the root.
+    pub fn iter(&self) -> Iter<'_> {
+        self.root.iter()
+    }
+}
+
+/// Pre-order iterator over the nodes of a [`Tree`] or below a [`Node`].
+pub struct Iter<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        // Push children in reverse so the first child is visited next
+        self.stack.extend(node.children.iter().rev());
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+impl Node {
+    /// A node without metadata at `path`, named and placed like the walk would.
+    pub(crate) fn stub(path: &str, kind: NodeKind, children: Vec<Node>) -> Node {
+        let path = PathBuf::from(path);
+        let depth = path.components().count() - 1;
+        let name = match path.file_name().filter(|_| depth > 0) {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => path.to_string_lossy().into_owned(),
+        };
+        Node {
+            name,
+            path,
+            depth,
+            kind,
+            link_target: None,
+            link_chain: Vec::new(),
+            broken: false,
+            metadata: None,
+            error: None,
+            children,
+            omitted: None,
+        }
+    }
+}